@@ -1,47 +1,94 @@
-use clap::{Arg, ArgGroup, ArgMatches, ArgAction, Command};
+use clap::{Arg, ArgAction, ArgGroup, ArgMatches, Command};
 
 pub struct RRRApp;
 
 impl RRRApp {
     pub fn get_matches() -> ArgMatches {
+        Self::command().get_matches()
+    }
+
+    /// Parser of the lines of `query --batch`: a query subcommand of `mode`
+    /// (`local` or `remote`) with its usual arguments, e.g.
+    /// `time -e model_instance_0.1 -c config -s`.
+    pub fn batch_line_parser(mode: &str) -> Command {
+        let command = Self::command();
+        let queries = command
+            .find_subcommand(mode)
+            .unwrap()
+            .get_subcommands()
+            .filter(|c| matches!(c.get_name(), "time" | "best-time" | "sol" | "nb-success"))
+            .cloned()
+            .collect::<Vec<_>>();
+        Command::new("batch")
+            .no_binary_name(true)
+            .subcommand_required(true)
+            .disable_help_subcommand(true)
+            .subcommands(queries)
+    }
+
+    fn command() -> Command {
         Command::new("rrr")
         .version(env!("CARGO_PKG_VERSION"))
         .about("Result Reader Rust")
+        .arg(
+            Arg::new("output_format")
+                .long("output-format")
+                .value_name("FORMAT")
+                .help("Format of query answers: text, json (one record per line) or csv")
+                .value_parser(["text", "json", "csv"])
+                .default_value("text")
+                .global(true)
+                .action(ArgAction::Set)
+        )
         .subcommand(Command::new("local")
             .about("Use RRR in local JSON file(s) mode")
             .arg(
                 Arg::new("input")
                     .short('i')
                     .long("input")
-                    .help("Sets the json file to use")
-                    .action(ArgAction::SetTrue)
-                    .required(true),
+                    .help("Sets the json file to use, needed by every subcommand but merge")
+                    .action(ArgAction::Set),
             )
             .arg(
                 Arg::new("decompress")
                     .short('d')
                     .long("decompress")
-                    .help("Set if JSON file is compressed with zstd")
+                    .help("Not needed anymore, compressed files are detected automatically")
+                    .action(ArgAction::SetTrue)
             )
             .arg(
                 Arg::new("folder")
                     .short('f')
                     .long("folder")
                     .help("Set if you want to give folder dump rather than single JSON file")
+                    .action(ArgAction::SetTrue)
             )
+            .arg(
+                Arg::new("jobs")
+                    .short('j')
+                    .long("jobs")
+                    .value_name("JOBS")
+                    .help("Number of threads reading and writing folder dumps, all cores by default")
+                    .value_parser(clap::value_parser!(usize))
+                    .action(ArgAction::Set)
+            )
+            .arg(Self::lock_timeout_arg())
+            .arg(Self::filter_arg())
             .subcommand(Command::new("time")
                 .about("Brings the exact min time of an instance")
                 .arg(
                     Arg::new("sr_time")
                         .short('r')
                         .long("savilerow-time")
-                        .help("Use SR time"),
+                        .help("Use SR time")
+                        .action(ArgAction::SetTrue),
                 )
                 .arg(
                     Arg::new("solver_time")
                         .short('s')
                         .long("solver-time")
-                        .help("Use solver time"),
+                        .help("Use solver time")
+                        .action(ArgAction::SetTrue),
                 )
                 .arg(
                     Arg::new("experiment_id")
@@ -49,7 +96,7 @@ impl RRRApp {
                         .long("experiment")
                         .value_name("EXPERIMENT")
                         .help("Give experiment id which is model_instance_freq")
-                        .action(ArgAction::SetTrue)
+                        .action(ArgAction::Set)
                         .required(true),
                 )
                 .arg(
@@ -58,12 +105,12 @@ impl RRRApp {
                         .long("config")
                         .value_name("CONFIG")
                         .help("Give config id which consists of many fields. miner_lite.py knows how to produce this.")
-                        .action(ArgAction::SetTrue)
+                        .action(ArgAction::Set)
                         .required(true),
                 )
                 .group(
                     ArgGroup::new("times")
-                        .args(&["sr_time", "solver_time"])
+                        .args(["sr_time", "solver_time"])
                         .required(true),
                 )
             )
@@ -75,34 +122,218 @@ impl RRRApp {
                         .long("add")
                         .value_name("SIDE_INPUT")
                         .help("Files to be consumed and merged")
-                        .action(ArgAction::SetTrue)
                         .action(ArgAction::Append)
                 )
+                .arg(
+                    Arg::new("archive")
+                        .long("archive")
+                        .value_name("ARCHIVE_DIR")
+                        .help("Moves the merged side files into this folder instead of deleting them")
+                        .action(ArgAction::Set)
+                        .requires("add")
+                )
+                .arg(
+                    Arg::new("from_db")
+                        .long("from-db")
+                        .value_name("DB_CONFIG")
+                        .help("Also merges every run of the db of this DB conf file")
+                        .action(ArgAction::Set)
+                        .conflicts_with("from_sqlite")
+                )
+                .arg(
+                    Arg::new("from_sqlite")
+                        .long("from-sqlite")
+                        .value_name("SQLITE_DB")
+                        .help("Also merges every run of this SQLite db file")
+                        .action(ArgAction::Set)
+                )
+                .arg(
+                    Arg::new("add_db_configs")
+                        .long("add-db-configs")
+                        .help("Also merges runs of configs the store does not have, with empty metadata")
+                        .action(ArgAction::SetTrue)
+                )
                 .arg(
                     Arg::new("pretty")
                         .short('p')
                         .long("pretty")
                         .help("Pretty prints the output json")
+                        .action(ArgAction::SetTrue)
                 )
                 .arg(
                     Arg::new("maintenance")
                         .short('m')
                         .long("maintenance")
                         .help("Apply maintenance to json for doubted values")
+                        .action(ArgAction::SetTrue)
+                )
+                .arg(
+                    Arg::new("compress")
+                        .short('c')
+                        .long("compress")
+                        .help("Set if you want to compress, with zstd unless --codec is given")
+                        .action(ArgAction::SetTrue)
+                )
+                .args(Self::compression_args())
+                .arg(
+                    Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .value_name("OUTPUT")
+                        .help("File to write on")
+                        .action(ArgAction::Set)
+                        .required(true),
+                )
+            )
+            .subcommand(Command::new("convert-format")
+                .about("Rewrites the store as json, folder dump or indexed store.")
+                .arg(
+                    Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .value_name("OUTPUT")
+                        .help("File or folder to write on")
+                        .action(ArgAction::Set)
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("to")
+                        .short('t')
+                        .long("to")
+                        .value_name("FORMAT")
+                        .help("Output format, indexed for .rrrs outputs and json otherwise by default")
+                        .value_parser(["json", "folder", "indexed"])
+                        .action(ArgAction::Set)
+                )
+                .arg(
+                    Arg::new("pretty")
+                        .short('p')
+                        .long("pretty")
+                        .help("Pretty prints the output json")
+                        .action(ArgAction::SetTrue)
+                )
+                .arg(
+                    Arg::new("compress")
+                        .short('c')
+                        .long("compress")
+                        .help("Set if you want to compress the json or folder files, with zstd unless --codec is given")
+                        .action(ArgAction::SetTrue)
+                )
+                .args(Self::compression_args())
+            )
+            .subcommand(Command::new("dedupe")
+                .about("Removes runs that were merged more than once.")
+                .arg(
+                    Arg::new("pretty")
+                        .short('p')
+                        .long("pretty")
+                        .help("Pretty prints the output json")
+                        .action(ArgAction::SetTrue)
                 )
                 .arg(
                     Arg::new("compress")
                         .short('c')
                         .long("compress")
-                        .help("Set if you want to compress with zstd")
+                        .help("Set if you want to compress, with zstd unless --codec is given")
+                        .action(ArgAction::SetTrue)
                 )
+                .args(Self::compression_args())
                 .arg(
                     Arg::new("output")
                         .short('o')
                         .long("output")
                         .value_name("OUTPUT")
                         .help("File to write on")
+                        .action(ArgAction::Set)
+                        .required(true),
+                )
+            )
+            .subcommand(Command::new("subset")
+                .about("Writes the configs or runs selected by --filter to a new store.")
+                .arg(
+                    Arg::new("pretty")
+                        .short('p')
+                        .long("pretty")
+                        .help("Pretty prints the output json")
+                        .action(ArgAction::SetTrue)
+                )
+                .arg(
+                    Arg::new("compress")
+                        .short('c')
+                        .long("compress")
+                        .help("Set if you want to compress, with zstd unless --codec is given")
+                        .action(ArgAction::SetTrue)
+                )
+                .args(Self::compression_args())
+                .arg(
+                    Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .value_name("OUTPUT")
+                        .help("File to write the selection on")
+                        .action(ArgAction::Set)
+                        .required(true),
+                )
+            )
+            .subcommand(Command::new("remove")
+                .about("Removes the experiments, configs or runs selected by --filter.")
+                .arg(
+                    Arg::new("pretty")
+                        .short('p')
+                        .long("pretty")
+                        .help("Pretty prints the output json")
+                        .action(ArgAction::SetTrue)
+                )
+                .arg(
+                    Arg::new("compress")
+                        .short('c')
+                        .long("compress")
+                        .help("Set if you want to compress, with zstd unless --codec is given")
+                        .action(ArgAction::SetTrue)
+                )
+                .args(Self::compression_args())
+                .arg(
+                    Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .value_name("OUTPUT")
+                        .help("File to write on, can be the input")
+                        .action(ArgAction::Set)
+                        .required(true),
+                )
+            )
+            .subcommand(Command::new("merge")
+                .about("Merges whole stores, reporting conflicting metadata.")
+                .arg(
+                    Arg::new("stores")
+                        .value_name("STORE")
+                        .help("Stores to merge, json, indexed, folder dumps or archives; the first wins conflicts")
+                        .num_args(2..)
+                        .action(ArgAction::Append)
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("pretty")
+                        .short('p')
+                        .long("pretty")
+                        .help("Pretty prints the output json")
+                        .action(ArgAction::SetTrue)
+                )
+                .arg(
+                    Arg::new("compress")
+                        .short('c')
+                        .long("compress")
+                        .help("Set if you want to compress, with zstd unless --codec is given")
                         .action(ArgAction::SetTrue)
+                )
+                .args(Self::compression_args())
+                .arg(
+                    Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .value_name("OUTPUT")
+                        .help("File to write on, can be one of the stores")
+                        .action(ArgAction::Set)
                         .required(true),
                 )
             )
@@ -114,7 +345,7 @@ impl RRRApp {
                         .long("output")
                         .value_name("OUTPUT")
                         .help("File to write on")
-                        .action(ArgAction::SetTrue)
+                        .action(ArgAction::Set)
                         .required(true),
                 )
                 .arg(
@@ -122,6 +353,7 @@ impl RRRApp {
                         .short('p')
                         .long("pretty")
                         .help("Pretty prints the output json")
+                        .action(ArgAction::SetTrue)
                 )
             )
             .subcommand(Command::new("csv-dump")
@@ -132,7 +364,7 @@ impl RRRApp {
                         .long("output")
                         .value_name("OUTPUT")
                         .help("File to write on")
-                        .action(ArgAction::SetTrue)
+                        .action(ArgAction::Set)
                         .required(true),
                 )
                 .arg(
@@ -140,6 +372,7 @@ impl RRRApp {
                         .short('t')
                         .long("table")
                         .help("Dumps table format CSV")
+                        .action(ArgAction::SetTrue)
                 )
             )
             .subcommand(Command::new("folder-dump")
@@ -150,14 +383,22 @@ impl RRRApp {
                         .long("output")
                         .value_name("OUTPUT")
                         .help("Folder to write")
-                        .action(ArgAction::SetTrue)
+                        .action(ArgAction::Set)
                         .required(true),
                 )
                 .arg(
                     Arg::new("compress")
                         .short('c')
                         .long("compress")
-                        .help("Set if you want to compress with zstd")
+                        .help("Set if you want to compress, with zstd unless --codec is given")
+                        .action(ArgAction::SetTrue)
+                )
+                .args(Self::compression_args())
+                .arg(
+                    Arg::new("dictionary")
+                        .long("dictionary")
+                        .help("Train a zstd dictionary on the experiments and compress every file with it, implies --compress")
+                        .action(ArgAction::SetTrue)
                 )
             )
             .subcommand(Command::new("sol")
@@ -168,7 +409,7 @@ impl RRRApp {
                         .long("experiment")
                         .value_name("EXPERIMENT")
                         .help("Give experiment id which is model_instance_freq")
-                        .action(ArgAction::SetTrue)
+                        .action(ArgAction::Set)
                         .required(true),
                 )
             )
@@ -180,48 +421,153 @@ impl RRRApp {
                         .long("experiment")
                         .value_name("EXPERIMENT")
                         .help("Give experiment id which is model_instance_freq")
-                        .action(ArgAction::SetTrue)
+                        .action(ArgAction::Set)
                         .required(true),
                 )
                 .arg(
                     Arg::new("sr_time")
                         .short('r')
                         .long("savilerow-time")
-                        .help("Use SR time"),
+                        .help("Use SR time")
+                        .action(ArgAction::SetTrue),
                 )
                 .arg(
                     Arg::new("solver_time")
                         .short('s')
                         .long("solver-time")
-                        .help("Use solver time"),
+                        .help("Use solver time")
+                        .action(ArgAction::SetTrue),
                 )
                 .group(
                     ArgGroup::new("times")
-                        .args(&["sr_time", "solver_time"])
+                        .args(["sr_time", "solver_time"])
                         .required(true),
                 )
             )
+            .subcommand(Command::new("nb-success")
+                .about("Checks the store to find how many distinct seed successful runs it has.")
+                .arg(
+                    Arg::new("experiment_id")
+                        .short('e')
+                        .long("experiment")
+                        .value_name("EXPERIMENT")
+                        .help("Give experiment id which is model_instance_freq")
+                        .action(ArgAction::Set)
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("config_id")
+                        .short('c')
+                        .long("config")
+                        .value_name("CONFIG")
+                        .help("Give config id which consists of many fields. miner_lite.py knows how to produce this")
+                        .action(ArgAction::Set)
+                        .required(true),
+                )
+            )
+            .subcommand(Command::new("query")
+                .about("Answers many queries at once, loading the store once")
+                .arg(
+                    Arg::new("batch")
+                        .short('b')
+                        .long("batch")
+                        .value_name("FILE")
+                        .help("File with one query per line, e.g. time -e EXPERIMENT -c CONFIG -s; stdin if not given or -")
+                        .action(ArgAction::Set)
+                )
+            )
         )
         // DB     
         .subcommand(Command::new("remote")
-            .about("Use RRR in remote MySQL DB mode")
+            .about("Use RRR in remote MySQL, PostgreSQL or SQLite DB mode")
             .arg(
                 Arg::new("db")
                     .short('d')
                     .long("db-config")
                     .value_name("DB_CONFIG")
                     .help("DB conf file")
+                    .action(ArgAction::Set),
+            )
+            .arg(
+                Arg::new("sqlite")
+                    .long("sqlite")
+                    .value_name("SQLITE_DB")
+                    .help("Use an embedded SQLite db file instead of the MySQL server")
+                    .action(ArgAction::Set),
+            )
+            .arg(
+                Arg::new("postgres")
+                    .long("postgres")
+                    .help("Connect to PostgreSQL rather than MySQL with the DB conf file")
                     .action(ArgAction::SetTrue)
+                    .requires("db"),
+            )
+            .group(
+                ArgGroup::new("database")
+                    .args(["db", "sqlite"])
                     .required(true),
             )
+            .arg(Self::filter_arg())
             .subcommand(Command::new("init")
-                .about("Init/clear the table and optionally populate from json")
+                .about("Creates the table if needed and optionally populates it from json")
                 .arg(
                     Arg::new("input")
                         .short('i')
                         .long("input")
                         .value_name("MAIN_JSON")
                         .help("Main storage file json file to populate the db")
+                        .action(ArgAction::Set)
+                )
+                .arg(
+                    Arg::new("mode")
+                        .short('m')
+                        .long("mode")
+                        .value_name("MODE")
+                        .help("What to do with runs already in the db")
+                        .value_parser(["append", "skip-duplicates", "replace-per-experiment"])
+                        .default_value("skip-duplicates")
+                        .action(ArgAction::Set)
+                )
+                .arg(
+                    Arg::new("drop")
+                        .long("drop")
+                        .help("Drops the existing table and every row in it first")
+                        .action(ArgAction::SetTrue)
+                )
+                .arg(
+                    Arg::new("yes")
+                        .short('y')
+                        .long("yes")
+                        .help("Does not ask for confirmation before dropping or replacing runs")
+                        .action(ArgAction::SetTrue)
+                )
+            )
+            .subcommand(Command::new("import")
+                .about("Adds the runs of a main storage json file to the db")
+                .arg(
+                    Arg::new("input")
+                        .short('i')
+                        .long("input")
+                        .value_name("MAIN_JSON")
+                        .help("Main storage file json file, compressed files are detected automatically")
+                        .action(ArgAction::Set)
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("mode")
+                        .short('m')
+                        .long("mode")
+                        .value_name("MODE")
+                        .help("What to do with runs already in the db")
+                        .value_parser(["append", "skip-duplicates", "replace-per-experiment"])
+                        .default_value("skip-duplicates")
+                        .action(ArgAction::Set)
+                )
+                .arg(
+                    Arg::new("yes")
+                        .short('y')
+                        .long("yes")
+                        .help("Does not ask for confirmation before replacing runs")
                         .action(ArgAction::SetTrue)
                 )
             )
@@ -233,7 +579,7 @@ impl RRRApp {
                     .long("experiment")
                     .value_name("EXPERIMENT")
                     .help("Give experiment id which is model_instance_freq")
-                    .action(ArgAction::SetTrue)
+                    .action(ArgAction::Set)
                     .required(true),
                 )
             )
@@ -245,9 +591,24 @@ impl RRRApp {
                         .long("experiment")
                         .value_name("EXPERIMENT")
                         .help("Give experiment id which is model_instance_freq")
-                        .action(ArgAction::SetTrue)
+                        .action(ArgAction::Set)
                         .required(true),
                 )
+                .arg(
+                    Arg::new("sr_time")
+                        .short('r')
+                        .long("savilerow-time")
+                        .help("Use SR time (default)")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("solver_time")
+                        .short('s')
+                        .long("solver-time")
+                        .help("Use solver time")
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("sr_time"),
+                )
             )
             .subcommand(Command::new("time")
                 .about("Finds the exact min time of an instance from the db")
@@ -257,7 +618,7 @@ impl RRRApp {
                         .long("experiment")
                         .value_name("EXPERIMENT")
                         .help("Give experiment id which is model_instance_freq")
-                        .action(ArgAction::SetTrue)
+                        .action(ArgAction::Set)
                         .required(true),
                 )
                 .arg(
@@ -266,9 +627,24 @@ impl RRRApp {
                         .long("config")
                         .value_name("CONFIG")
                         .help("Give config id which consists of many fields. miner_lite.py knows how to produce this")
-                        .action(ArgAction::SetTrue)
+                        .action(ArgAction::Set)
                         .required(true),
                 )
+                .arg(
+                    Arg::new("sr_time")
+                        .short('r')
+                        .long("savilerow-time")
+                        .help("Use SR time (default)")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("solver_time")
+                        .short('s')
+                        .long("solver-time")
+                        .help("Use solver time")
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("sr_time"),
+                )
             )
             .subcommand(Command::new("nb-success")
                 .about("Checks the db to find how many distinct seed successful runs on db.")
@@ -278,7 +654,7 @@ impl RRRApp {
                         .long("experiment")
                         .value_name("EXPERIMENT")
                         .help("Give experiment id which is model_instance_freq")
-                        .action(ArgAction::SetTrue)
+                        .action(ArgAction::Set)
                         .required(true),
                 )
                 .arg(
@@ -287,10 +663,59 @@ impl RRRApp {
                         .long("config")
                         .value_name("CONFIG")
                         .help("Give config id which consists of many fields. miner_lite.py knows how to produce this")
-                        .action(ArgAction::SetTrue)
+                        .action(ArgAction::Set)
                         .required(true),
                 )
             )
+            .subcommand(Command::new("query")
+                .about("Answers many queries at once, fetching their runs in a single db query")
+                .arg(
+                    Arg::new("batch")
+                        .short('b')
+                        .long("batch")
+                        .value_name("FILE")
+                        .help("File with one query per line, e.g. time -e EXPERIMENT -c CONFIG -s; stdin if not given or -")
+                        .action(ArgAction::Set)
+                )
+            )
+            .subcommand(Command::new("migrate")
+                .about("Applies pending schema migrations to the db, keeping its rows")
+                .alias("upgrade")
+            )
+            .subcommand(Command::new("export")
+                .about("Exports the db back to a main storage json file")
+                .arg(
+                    Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .value_name("OUTPUT")
+                        .help("File to write on, compressed if it ends with .zst, .gz or .xz")
+                        .action(ArgAction::Set)
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("pretty")
+                        .short('p')
+                        .long("pretty")
+                        .help("Pretty prints the output json")
+                        .action(ArgAction::SetTrue)
+                )
+                .arg(
+                    Arg::new("split_ids")
+                        .long("split-ids")
+                        .help("Fills model, instance and freq from model_instance_freq experiment ids")
+                        .action(ArgAction::SetTrue)
+                )
+                .arg(
+                    Arg::new("compress")
+                        .short('c')
+                        .long("compress")
+                        .help("Set if you want to compress, with zstd unless --codec is given")
+                        .action(ArgAction::SetTrue)
+                )
+                .args(Self::compression_args())
+                .arg(Self::lock_timeout_arg())
+            )
             .subcommand(Command::new("commit")
                 .about("Commits the new entry to db")
                 .arg(
@@ -299,11 +724,55 @@ impl RRRApp {
                         .long("add")
                         .value_name("EXPERIMENT")
                         .help("Give single experiment in json format.")
-                        .action(ArgAction::SetTrue)
+                        .action(ArgAction::Set)
                         .required(true),
                 )
             )
         )
-        .get_matches()
+    }
+
+    /// Codec, level and long mode of the subcommands writing compressed
+    /// stores.
+    fn compression_args() -> [Arg; 3] {
+        [
+            Arg::new("codec")
+                .long("codec")
+                .value_name("CODEC")
+                .help("Compress with this codec: zstd, gzip or xz, implies --compress")
+                .value_parser(["zstd", "gzip", "xz"])
+                .action(ArgAction::Set),
+            Arg::new("level")
+                .long("level")
+                .value_name("LEVEL")
+                .help("Compression level, the codec default otherwise (zstd 1, gzip and xz 6), implies --compress")
+                .value_parser(clap::value_parser!(i32))
+                .allow_negative_numbers(true)
+                .action(ArgAction::Set),
+            Arg::new("long")
+                .long("long")
+                .help("Use the zstd long distance mode, better on large stores, implies --compress")
+                .action(ArgAction::SetTrue),
+        ]
+    }
+
+    /// Wait for the lock of a path another rrr is writing.
+    fn lock_timeout_arg() -> Arg {
+        Arg::new("lock_timeout")
+            .long("lock-timeout")
+            .value_name("SECONDS")
+            .help("How long to wait for another rrr writing the same store or output")
+            .value_parser(clap::value_parser!(u64))
+            .default_value("600")
+            .action(ArgAction::Set)
+    }
+
+    /// Filter restricting a command to some experiments and configs.
+    fn filter_arg() -> Arg {
+        Arg::new("filter")
+            .long("filter")
+            .value_name("EXPR")
+            .global(true)
+            .help("Only use the configs selected, e.g. 'model == \"x\" && solver =~ \"kissat\" && !cgroups'. Fields: exp_id, config_id, model, instance, freq, preprocess, representation, solver, the config flags and the run fields status, machine_info, seed, memory_limit, time_limit")
+            .action(ArgAction::Set)
     }
 }
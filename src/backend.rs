@@ -0,0 +1,515 @@
+use serde::{Serialize, Serializer};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use crate::json::{BestExperimentResult, ExperimentMultiple, ExperimentStore, SolveInformation};
+use crate::utils::{self, FSort, Mode};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunStatus {
+    Success,
+    Doubted,
+    Timeout,
+    Memout,
+    Crash,
+}
+
+impl RunStatus {
+    /// Name used for `result_type` in the db and `type` in the json.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RunStatus::Success => "SUCCESS",
+            RunStatus::Doubted => "DOUBTED",
+            RunStatus::Timeout => "TIMEOUT",
+            RunStatus::Memout => "MEMOUT",
+            RunStatus::Crash => "CRASHED",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "SUCCESS" => Some(RunStatus::Success),
+            "DOUBTED" => Some(RunStatus::Doubted),
+            "TIMEOUT" => Some(RunStatus::Timeout),
+            "MEMOUT" => Some(RunStatus::Memout),
+            "CRASHED" => Some(RunStatus::Crash),
+            _ => None,
+        }
+    }
+}
+
+/// Backend independent view of a single solve. `sr_time` is the measured time
+/// of the run: total SR time for successes, the time limit for timeouts and
+/// the crash time for memouts and crashes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Run {
+    pub config_id: String,
+    pub status: RunStatus,
+    pub sr_time: f64,
+    pub solver_time: Option<f64>,
+    pub nb_solutions: Option<u64>,
+    pub seed: Option<f64>,
+}
+
+impl Run {
+    pub fn from_solve(config_id: &str, solve: &SolveInformation) -> Self {
+        let (status, sr_time, solver_time, nb_solutions, seed) = match solve {
+            SolveInformation::Success {
+                total_sr_time,
+                total_solver_time,
+                nb_solutions,
+                seed,
+                ..
+            } => (
+                RunStatus::Success,
+                *total_sr_time,
+                Some(*total_solver_time),
+                Some(*nb_solutions),
+                *seed,
+            ),
+            SolveInformation::Doubted {
+                total_sr_time,
+                total_solver_time,
+                nb_solutions,
+                seed,
+                ..
+            } => (
+                RunStatus::Doubted,
+                *total_sr_time,
+                Some(*total_solver_time),
+                Some(*nb_solutions),
+                *seed,
+            ),
+            SolveInformation::Timeout {
+                time_limit, seed, ..
+            } => (RunStatus::Timeout, *time_limit as f64, None, None, *seed),
+            SolveInformation::Memout {
+                crash_time, seed, ..
+            } => (RunStatus::Memout, *crash_time, None, None, *seed),
+            SolveInformation::Crash {
+                crash_time, seed, ..
+            } => (RunStatus::Crash, *crash_time, None, None, *seed),
+        };
+        Run {
+            config_id: config_id.to_string(),
+            status,
+            sr_time,
+            solver_time,
+            nb_solutions,
+            seed,
+        }
+    }
+}
+
+/// Best result over a set of runs. Successful runs win with their minimum
+/// times, otherwise the longest timeout, memout or crash in that order.
+/// Doubted runs are never trusted.
+pub fn best_of<'a>(runs: impl IntoIterator<Item = &'a Run>) -> BestExperimentResult {
+    let mut res_sr = vec![];
+    let mut res_solver = vec![];
+    let mut res_timeout = vec![];
+    let mut res_memout = vec![];
+    let mut res_crash = vec![];
+    let mut res_nb_solutions: Option<u64> = None;
+    for r in runs {
+        match r.status {
+            RunStatus::Success => {
+                res_sr.push(r.sr_time);
+                if let Some(t) = r.solver_time {
+                    res_solver.push(t);
+                }
+                if res_nb_solutions.is_none() {
+                    res_nb_solutions = r.nb_solutions;
+                }
+            }
+            RunStatus::Timeout => res_timeout.push(r.sr_time),
+            RunStatus::Memout => res_memout.push(r.sr_time),
+            RunStatus::Crash => res_crash.push(r.sr_time),
+            RunStatus::Doubted => (),
+        }
+    }
+    if !res_sr.is_empty() {
+        res_sr.f_sort();
+        res_solver.f_sort();
+        BestExperimentResult::Success {
+            min_total_sr_time: res_sr[0],
+            min_total_solver_time: res_solver.first().copied(),
+            nb_solutions: res_nb_solutions.unwrap_or(0),
+        }
+    } else if !res_timeout.is_empty() {
+        res_timeout.f_sort();
+        BestExperimentResult::Timeout {
+            max_time_limit: res_timeout[res_timeout.len() - 1] as u64,
+        }
+    } else if !res_memout.is_empty() {
+        res_memout.f_sort();
+        BestExperimentResult::Memout {
+            max_crash_time: res_memout[res_memout.len() - 1],
+        }
+    } else if !res_crash.is_empty() {
+        res_crash.f_sort();
+        BestExperimentResult::Crash {
+            max_crash_time: res_crash[res_crash.len() - 1],
+        }
+    } else {
+        BestExperimentResult::None
+    }
+}
+
+/// One `time`, `best-time`, `sol` or `nb-success` query. The mode only picks
+/// which time the text output shows.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Query {
+    Time {
+        exp_id: String,
+        config_id: String,
+        mode: Mode,
+    },
+    BestTime {
+        exp_id: String,
+        mode: Mode,
+    },
+    Sol {
+        exp_id: String,
+    },
+    NbSuccess {
+        exp_id: String,
+        config_id: String,
+    },
+}
+
+impl Query {
+    pub fn exp_id(&self) -> &str {
+        match self {
+            Query::Time { exp_id, .. }
+            | Query::BestTime { exp_id, .. }
+            | Query::Sol { exp_id }
+            | Query::NbSuccess { exp_id, .. } => exp_id,
+        }
+    }
+
+    pub fn config_id(&self) -> Option<&str> {
+        match self {
+            Query::Time { config_id, .. } | Query::NbSuccess { config_id, .. } => Some(config_id),
+            Query::BestTime { .. } | Query::Sol { .. } => None,
+        }
+    }
+}
+
+/// Answer of any query in one shape, for machine readable output. `status`
+/// is the one of the best result or `EMPTY`, times are its min times or the
+/// max time limit or crash time, `support` is the number of successful runs
+/// agreeing on `nb_solutions` and `seeds` the distinct seeds with a
+/// successful run.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct QueryRecord {
+    pub exp_id: String,
+    pub config_id: Option<String>,
+    #[serde(serialize_with = "serialize_status")]
+    pub status: Option<RunStatus>,
+    pub sr_time: Option<f64>,
+    pub solver_time: Option<f64>,
+    pub nb_solutions: Option<u64>,
+    pub support: Option<usize>,
+    pub seeds: Vec<f64>,
+}
+
+fn serialize_status<S: Serializer>(status: &Option<RunStatus>, s: S) -> Result<S::Ok, S::Error> {
+    s.serialize_str(status_name(*status))
+}
+
+/// Name of a record status, `EMPTY` when there are no runs.
+pub fn status_name(status: Option<RunStatus>) -> &'static str {
+    status.map_or("EMPTY", |s| s.as_str())
+}
+
+impl QueryRecord {
+    pub fn from_runs(query: &Query, runs: &[Run]) -> Self {
+        let (status, sr_time, solver_time) = match best_of(runs) {
+            BestExperimentResult::Success {
+                min_total_sr_time,
+                min_total_solver_time,
+                ..
+            } => (
+                Some(RunStatus::Success),
+                Some(min_total_sr_time),
+                min_total_solver_time,
+            ),
+            BestExperimentResult::Timeout { max_time_limit } => {
+                (Some(RunStatus::Timeout), Some(max_time_limit as f64), None)
+            }
+            BestExperimentResult::Memout { max_crash_time } => {
+                (Some(RunStatus::Memout), Some(max_crash_time), None)
+            }
+            BestExperimentResult::Crash { max_crash_time } => {
+                (Some(RunStatus::Crash), Some(max_crash_time), None)
+            }
+            BestExperimentResult::None => (None, None, None),
+        };
+        let (nb_solutions, support) = solutions_of(runs).unzip();
+        let mut seeds = vec![];
+        for r in runs {
+            if let (RunStatus::Success, Some(seed)) = (r.status, r.seed) {
+                if !seeds.contains(&seed) {
+                    seeds.push(seed);
+                }
+            }
+        }
+        QueryRecord {
+            exp_id: query.exp_id().to_string(),
+            config_id: query.config_id().map(|c| c.to_string()),
+            status,
+            sr_time,
+            solver_time,
+            nb_solutions,
+            support,
+            seeds,
+        }
+    }
+}
+
+/// Number of solutions of the first successful run together with the number
+/// of successful runs agreeing with it.
+fn solutions_of<'a>(runs: impl IntoIterator<Item = &'a Run>) -> Option<(u64, usize)> {
+    let mut nb_solutions: Option<u64> = None;
+    let mut support = 0;
+    for r in runs {
+        if let (RunStatus::Success, Some(nb)) = (r.status, r.nb_solutions) {
+            match nb_solutions {
+                Some(n) if n == nb => support += 1,
+                Some(_) => (),
+                None => {
+                    nb_solutions = Some(nb);
+                    support = 1;
+                }
+            }
+        }
+    }
+    nb_solutions.map(|nb| (nb, support))
+}
+
+/// Relaxed subgraph experiments are split over several experiment ids; the
+/// best time of one of them has to consider all of its siblings.
+pub fn get_rsd_extra_exp_ids(exp_id: &str) -> Vec<String> {
+    if !exp_id.contains("rel_sub") {
+        return vec![exp_id.to_string()];
+    }
+    let mut naked_str = exp_id.replace("rel_sub", "");
+    naked_str = naked_str.replace("_complete", "");
+    naked_str = naked_str.replace("_par_neg", "");
+    naked_str = naked_str.replace("_par_pos", "");
+    let mut vec = vec![];
+    for i in 0..4 {
+        let mut base = String::from("rel_sub");
+        match i {
+            1 => base.push_str("_complete"),
+            2 => base.push_str("_par_neg"),
+            3 => base.push_str("_par_pos"),
+            _ => {}
+        }
+        base.push_str(&naked_str);
+        vec.push(base);
+    }
+    vec
+}
+
+/// Source of experiment results. Implementors only provide access to the raw
+/// runs; every query is answered by the provided methods so that all
+/// backends share the same semantics.
+pub trait ResultBackend {
+    /// All runs of an experiment over every config. Unknown ids give no runs.
+    fn experiment_runs(&mut self, exp_id: &str) -> Vec<Run>;
+
+    /// All runs of a single config of an experiment.
+    fn config_runs(&mut self, exp_id: &str, config_id: &str) -> Vec<Run> {
+        self.experiment_runs(exp_id)
+            .into_iter()
+            .filter(|r| r.config_id == config_id)
+            .collect()
+    }
+
+    /// Best result of one config of an experiment.
+    fn best_time(&mut self, exp_id: &str, config_id: &str) -> BestExperimentResult {
+        best_of(&self.config_runs(exp_id, config_id))
+    }
+
+    /// Virtual best of an experiment over all of its configs.
+    fn virtual_best(&mut self, exp_id: &str) -> BestExperimentResult {
+        let mut runs = vec![];
+        for e in get_rsd_extra_exp_ids(exp_id) {
+            runs.extend(self.experiment_runs(&e));
+        }
+        best_of(&runs)
+    }
+
+    /// Number of solutions of an experiment together with the number of
+    /// successful runs agreeing with it.
+    fn nb_solutions(&mut self, exp_id: &str) -> Option<(u64, usize)> {
+        solutions_of(&self.experiment_runs(exp_id))
+    }
+
+    /// Number of distinct seeds with a successful run of a config.
+    fn nb_successful(&mut self, exp_id: &str, config_id: &str) -> usize {
+        let mut seed_vec = vec![];
+        for r in self.config_runs(exp_id, config_id) {
+            if let (RunStatus::Success, Some(seed)) = (r.status, r.seed) {
+                if !seed_vec.contains(&seed) {
+                    seed_vec.push(seed);
+                }
+            }
+        }
+        seed_vec.len()
+    }
+
+    /// Runs a query is answered from: the config for `time` and
+    /// `nb-success`, every config and relaxed subgraph sibling for
+    /// `best-time`, every config for `sol`.
+    fn runs_of(&mut self, query: &Query) -> Vec<Run> {
+        match query {
+            Query::Time {
+                exp_id, config_id, ..
+            }
+            | Query::NbSuccess { exp_id, config_id } => self.config_runs(exp_id, config_id),
+            Query::BestTime { exp_id, .. } => {
+                let mut runs = vec![];
+                for e in get_rsd_extra_exp_ids(exp_id) {
+                    runs.extend(self.experiment_runs(&e));
+                }
+                runs
+            }
+            Query::Sol { exp_id } => self.experiment_runs(exp_id),
+        }
+    }
+
+    /// Answer of a query as a record.
+    fn answer(&mut self, query: &Query) -> QueryRecord {
+        QueryRecord::from_runs(query, &self.runs_of(query))
+    }
+
+    /// Runs of many experiments. Backends fetching runs remotely override it
+    /// to get them all at once.
+    fn batch_runs(&mut self, exp_ids: &[String]) -> RunCache {
+        let mut runs = vec![];
+        for exp_id in exp_ids {
+            for r in self.experiment_runs(exp_id) {
+                runs.push((exp_id.clone(), r));
+            }
+        }
+        RunCache::new(runs)
+    }
+
+    /// Answers of a batch of queries. Backends overriding `batch_runs`
+    /// answer from `self.batch_runs(&batch_exp_ids(queries))` instead.
+    fn answer_all(&mut self, queries: &[Query]) -> Vec<QueryRecord> {
+        queries.iter().map(|q| self.answer(q)).collect()
+    }
+}
+
+/// Every experiment a batch of queries reads, relaxed subgraph siblings
+/// included.
+pub fn batch_exp_ids(queries: &[Query]) -> Vec<String> {
+    let mut seen = HashSet::new();
+    queries
+        .iter()
+        .flat_map(|q| get_rsd_extra_exp_ids(q.exp_id()))
+        .filter(|e| seen.insert(e.clone()))
+        .collect()
+}
+
+/// Runs fetched beforehand, grouped by experiment, to answer a batch of
+/// queries without going back to the backend.
+pub struct RunCache {
+    runs: HashMap<String, Vec<Run>>,
+}
+
+impl RunCache {
+    pub fn new<I: IntoIterator<Item = (String, Run)>>(runs: I) -> Self {
+        let mut cache = RunCache {
+            runs: HashMap::new(),
+        };
+        for (exp_id, run) in runs {
+            cache.runs.entry(exp_id).or_default().push(run);
+        }
+        cache
+    }
+
+    /// Keeps only the runs for which `keep` holds.
+    pub fn retain<F: Fn(&str, &Run) -> bool>(&mut self, keep: F) {
+        for (exp_id, runs) in self.runs.iter_mut() {
+            runs.retain(|r| keep(exp_id, r));
+        }
+    }
+}
+
+impl ResultBackend for RunCache {
+    fn experiment_runs(&mut self, exp_id: &str) -> Vec<Run> {
+        self.runs.get(exp_id).cloned().unwrap_or_default()
+    }
+}
+
+impl ExperimentMultiple {
+    pub fn runs(&self) -> Vec<Run> {
+        let mut runs = vec![];
+        for (c_id, config) in &self.configs {
+            for solve in &config.solve_information {
+                runs.push(Run::from_solve(c_id, solve));
+            }
+        }
+        runs
+    }
+}
+
+impl ResultBackend for ExperimentStore {
+    fn experiment_runs(&mut self, exp_id: &str) -> Vec<Run> {
+        self.experiments
+            .get(exp_id)
+            .map_or_else(Vec::new, |e| e.runs())
+    }
+}
+
+/// Folder dump backend. Only the file of the requested experiment is read.
+pub struct FolderBackend {
+    folder: String,
+    dictionaries: utils::Dictionaries,
+}
+
+impl FolderBackend {
+    pub fn new(folder: &str) -> Self {
+        FolderBackend {
+            folder: folder.to_string(),
+            dictionaries: utils::read_dictionaries(folder),
+        }
+    }
+
+    /// Reads the file of one experiment, compressed or not, if the folder
+    /// has it.
+    pub fn load(&self, exp_id: &str) -> Option<ExperimentMultiple> {
+        let path = utils::json_suffixes()
+            .iter()
+            .map(|s| Path::new(&self.folder).join(format!("{}{}", exp_id, s)))
+            .find(|p| p.is_file())?;
+        let reader =
+            utils::open_file_with_dictionaries(&path.display().to_string(), &self.dictionaries);
+        Some(serde_json::from_reader(reader).unwrap())
+    }
+
+    /// Store of only the given experiments, those missing from the folder
+    /// are left out.
+    pub fn load_store<'a>(&self, exp_ids: impl IntoIterator<Item = &'a str>) -> ExperimentStore {
+        let mut store = ExperimentStore {
+            experiments: HashMap::new(),
+        };
+        for exp_id in exp_ids {
+            if let Some(e) = self.load(exp_id) {
+                store.experiments.insert(exp_id.to_string(), Box::new(e));
+            }
+        }
+        store
+    }
+}
+
+impl ResultBackend for FolderBackend {
+    fn experiment_runs(&mut self, exp_id: &str) -> Vec<Run> {
+        self.load(exp_id).map_or_else(Vec::new, |e| e.runs())
+    }
+}
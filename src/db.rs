@@ -1,31 +1,56 @@
 // use mysql::prelude::*;
 // use mysql::*;
-use mysql_async::{prelude::*, Conn, Pool};
+use mysql_async::{prelude::*, Conn, Pool, Row, Transaction, TxOpts};
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::thread;
 use std::time::Duration;
+use tokio::runtime::Runtime;
 
-use crate::json::{ExperimentSingle, ExperimentStore, SolveInformation};
-use crate::utils::Mode;
+use crate::backend::{batch_exp_ids, Query, QueryRecord, ResultBackend, Run, RunCache, RunStatus};
+use crate::json::{
+    self, ConfigMultiple, ExperimentMultiple, ExperimentSingle, ExperimentStore, LevelInformation,
+    SolveInformation,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DBKind {
+    MySql,
+    Postgres,
+}
 
 pub struct DBConfig<'a> {
-    host: &'a str,
-    username: &'a str,
-    password: &'a str,
+    pub host: &'a str,
+    pub username: &'a str,
+    pub password: &'a str,
+    pub kind: DBKind,
+    database: Option<&'a str>,
 }
 
 impl<'a> DBConfig<'a> {
-    fn new(host: &'a str, username: &'a str, password: &'a str) -> DBConfig<'a> {
+    fn new(
+        host: &'a str,
+        username: &'a str,
+        password: &'a str,
+        kind: DBKind,
+        database: Option<&'a str>,
+    ) -> DBConfig<'a> {
         DBConfig {
             host,
             username,
             password,
+            kind,
+            database,
         }
     }
 
+    /// Reads `key=value` lines. `host`, `user` and `password` are required,
+    /// `backend` (`mysql` or `postgres`) and `database` are optional.
     pub fn from_config_file(file_contents: &'a str) -> Self {
         let mut host = None;
         let mut username = None;
         let mut password = None;
+        let mut kind = DBKind::MySql;
+        let mut database = None;
         for line in file_contents.lines() {
             if line.starts_with('#') {
                 continue;
@@ -38,6 +63,14 @@ impl<'a> DBConfig<'a> {
                     username = Some(split[1].trim_end())
                 } else if split[0] == "password" {
                     password = Some(split[1].trim_end())
+                } else if split[0] == "database" {
+                    database = Some(split[1].trim_end())
+                } else if split[0] == "backend" {
+                    kind = match split[1].trim_end() {
+                        "mysql" => DBKind::MySql,
+                        "postgres" | "postgresql" => DBKind::Postgres,
+                        other => panic!("Unknown backend {} in db config", other),
+                    }
                 }
             }
         }
@@ -45,8 +78,18 @@ impl<'a> DBConfig<'a> {
             host.expect("host not found"),
             username.expect("username not found"),
             password.expect("password not found"),
+            kind,
+            database,
         )
     }
+
+    /// Database to connect to, `<user>_experiments` unless given.
+    pub fn database_name(&self) -> String {
+        match self.database {
+            Some(d) => d.to_string(),
+            None => format!("{}_experiments", self.username),
+        }
+    }
 }
 
 pub async fn init_db_connection(db: DBConfig<'_>) -> Conn {
@@ -61,16 +104,18 @@ pub async fn init_db_connection(db: DBConfig<'_>) -> Conn {
 
 async fn try_init_db_connection<'a>(db: &'a DBConfig<'a>) -> Result<Conn, mysql_async::Error> {
     let url = format!(
-        "mysql://{}:{}@{}/{}_experiments",
-        db.username, db.password, db.host, db.username
+        "mysql://{}:{}@{}/{}",
+        db.username,
+        db.password,
+        db.host,
+        db.database_name()
     );
     let pool = Pool::from_url(url)?;
     let conn = pool.get_conn();
     conn.await
 }
 
-pub async fn create_table(conn: &mut Conn) {
-    // Let's create a table for payments.
+async fn create_base_table(conn: &mut Conn) {
     conn.query_drop(
         r"CREATE TABLE experiments (
             id SERIAL PRIMARY KEY,
@@ -89,29 +134,58 @@ pub async fn create_table(conn: &mut Conn) {
     .unwrap();
 }
 
-pub async fn drop_table(conn: &mut Conn) {
-    conn.query_drop(
-        r"DROP TABLE IF EXISTS experiments
-        ",
-    )
-    .await
-    .unwrap();
+async fn has_table(conn: &mut Conn, table: &str) -> bool {
+    let count: Option<u64> = conn
+        .exec_first(
+            "SELECT COUNT(*) FROM information_schema.tables WHERE table_schema = DATABASE() AND table_name = ?",
+            (table,),
+        )
+        .await
+        .unwrap();
+    count.unwrap_or(0) > 0
+}
+
+async fn has_index(conn: &mut Conn, table: &str, index: &str) -> bool {
+    let count: Option<u64> = conn
+        .exec_first(
+            "SELECT COUNT(*) FROM information_schema.statistics WHERE table_schema = DATABASE() AND table_name = ? AND index_name = ?",
+            (table, index),
+        )
+        .await
+        .unwrap();
+    count.unwrap_or(0) > 0
+}
+
+async fn has_column(conn: &mut Conn, table: &str, column: &str) -> bool {
+    let count: Option<u64> = conn
+        .exec_first(
+            "SELECT COUNT(*) FROM information_schema.columns WHERE table_schema = DATABASE() AND table_name = ? AND column_name = ?",
+            (table, column),
+        )
+        .await
+        .unwrap();
+    count.unwrap_or(0) > 0
 }
 
 pub struct DBRow {
-    exp_id: String,
-    config_id: String,
-    result_type: String,
-    measured_time: f64,
-    nb_solutions: Option<u64>,
-    machine_info: String,
-    memory_limit: u64,
-    seed: Option<f64>,
+    pub exp_id: String,
+    pub config_id: String,
+    pub result_type: String,
+    pub measured_time: f64,
+    pub nb_solutions: Option<u64>,
+    pub machine_info: String,
+    pub memory_limit: u64,
+    pub seed: Option<f64>,
+    pub solver_time: Option<f64>,
+    pub total_nodes: Option<u64>,
+    pub time_limit: Option<u64>,
+    pub freq_nb_solutions: Option<u64>,
+    pub level_info: Option<Box<LevelInformation>>,
 }
 
 #[allow(clippy::too_many_arguments)]
 impl DBRow {
-    fn new(
+    pub fn new(
         exp_id: String,
         config_id: String,
         result_type: String,
@@ -130,17 +204,109 @@ impl DBRow {
             machine_info,
             memory_limit,
             seed,
+            solver_time: None,
+            total_nodes: None,
+            time_limit: None,
+            freq_nb_solutions: None,
+            level_info: None,
         }
     }
 
-    fn from_solve(exp_id: &str, config_id: &str, solve_information: &SolveInformation) -> Self {
+    /// Sets the columns that are not part of the original table.
+    pub fn with_details(
+        mut self,
+        solver_time: Option<f64>,
+        total_nodes: Option<u64>,
+        time_limit: Option<u64>,
+        freq_nb_solutions: Option<u64>,
+    ) -> Self {
+        self.solver_time = solver_time;
+        self.total_nodes = total_nodes;
+        self.time_limit = time_limit;
+        self.freq_nb_solutions = freq_nb_solutions;
+        self
+    }
+
+    /// Inverse of `from_solve`. Rows written before the table was upgraded
+    /// have no time limit, and successes no solver time nor number of
+    /// solutions either: no run can be rebuilt from them, so they give `None`.
+    pub fn to_solve(&self) -> Option<SolveInformation> {
+        let level_info = || self.level_info.clone().unwrap_or_default();
+        let solve = match self.result_type.as_str() {
+            "SUCCESS" => SolveInformation::Success {
+                total_solver_time: self.solver_time?,
+                total_sr_time: self.measured_time,
+                total_nodes: self.total_nodes,
+                nb_solutions: self.nb_solutions?,
+                seed: self.seed,
+                memory_limit: self.memory_limit,
+                time_limit: self.time_limit?,
+                machine_info: self.machine_info.clone(),
+                level_info: level_info(),
+                freq_nb_solutions: self.freq_nb_solutions,
+            },
+            "DOUBTED" => SolveInformation::Doubted {
+                total_solver_time: self.solver_time?,
+                total_sr_time: self.measured_time,
+                total_nodes: self.total_nodes,
+                nb_solutions: self.nb_solutions?,
+                seed: self.seed,
+                memory_limit: self.memory_limit,
+                time_limit: self.time_limit?,
+                machine_info: self.machine_info.clone(),
+                level_info: level_info(),
+                freq_nb_solutions: self.freq_nb_solutions,
+            },
+            "TIMEOUT" => SolveInformation::Timeout {
+                seed: self.seed,
+                memory_limit: self.memory_limit,
+                time_limit: self.time_limit?,
+                machine_info: self.machine_info.clone(),
+            },
+            "MEMOUT" => SolveInformation::Memout {
+                seed: self.seed,
+                memory_limit: self.memory_limit,
+                time_limit: self.time_limit?,
+                machine_info: self.machine_info.clone(),
+                crash_time: self.measured_time,
+            },
+            "CRASHED" => SolveInformation::Crash {
+                seed: self.seed,
+                memory_limit: self.memory_limit,
+                time_limit: self.time_limit,
+                machine_info: self.machine_info.clone(),
+                crash_time: self.measured_time,
+            },
+            other => panic!("Unknown result_type {} in db", other),
+        };
+        Some(solve)
+    }
+
+    /// Single run experiment as written by a job, metadata included as far
+    /// as the table keeps it. `None` for rows `to_solve` cannot rebuild.
+    pub fn to_experiment(&self) -> Option<ExperimentSingle> {
+        let solve = self.to_solve()?;
+        let (model, instance, freq) = split_exp_id(&self.exp_id);
+        Some(ExperimentSingle {
+            model,
+            instance,
+            freq,
+            exp_id: self.exp_id.clone(),
+            config_id: self.config_id.clone(),
+            config: Box::new(ConfigMultiple {
+                solve_information: vec![solve],
+                ..Default::default()
+            }),
+        })
+    }
+
+    pub fn from_solve(exp_id: &str, config_id: &str, solve_information: &SolveInformation) -> Self {
         let measured_time;
         let mut nb: Option<u64> = None;
         let r_seed: Option<f64>;
         let r_machine_info;
-        let result_type;
         let r_memory_limit;
-        match solve_information {
+        let result_type = match solve_information {
             SolveInformation::Success {
                 total_sr_time,
                 nb_solutions,
@@ -154,7 +320,7 @@ impl DBRow {
                 r_seed = *seed;
                 r_machine_info = machine_info.as_str();
                 r_memory_limit = *memory_limit;
-                result_type = "SUCCESS"
+                "SUCCESS"
             }
             SolveInformation::Timeout {
                 seed,
@@ -166,7 +332,7 @@ impl DBRow {
                 measured_time = *time_limit as f64;
                 r_machine_info = machine_info.as_str();
                 r_memory_limit = *memory_limit;
-                result_type = "TIMEOUT"
+                "TIMEOUT"
             }
             SolveInformation::Memout {
                 crash_time,
@@ -179,7 +345,7 @@ impl DBRow {
                 measured_time = *crash_time;
                 r_machine_info = machine_info.as_str();
                 r_memory_limit = *memory_limit;
-                result_type = "MEMOUT"
+                "MEMOUT"
             }
             SolveInformation::Crash {
                 crash_time,
@@ -192,7 +358,7 @@ impl DBRow {
                 measured_time = *crash_time;
                 r_machine_info = machine_info.as_str();
                 r_memory_limit = *memory_limit;
-                result_type = "CRASHED"
+                "CRASHED"
             }
             SolveInformation::Doubted {
                 total_sr_time,
@@ -207,10 +373,10 @@ impl DBRow {
                 r_seed = *seed;
                 r_machine_info = machine_info.as_str();
                 r_memory_limit = *memory_limit;
-                result_type = "DOUBTED"
+                "DOUBTED"
             }
-        }
-        DBRow::new(
+        };
+        let row = DBRow::new(
             exp_id.to_string(),
             config_id.to_string(),
             result_type.to_string(),
@@ -219,195 +385,618 @@ impl DBRow {
             r_machine_info.to_string(),
             r_memory_limit,
             r_seed,
-        )
+        );
+        match solve_information {
+            SolveInformation::Success {
+                total_solver_time,
+                total_nodes,
+                time_limit,
+                level_info,
+                freq_nb_solutions,
+                ..
+            }
+            | SolveInformation::Doubted {
+                total_solver_time,
+                total_nodes,
+                time_limit,
+                level_info,
+                freq_nb_solutions,
+                ..
+            } => DBRow {
+                level_info: Some(level_info.clone()),
+                ..row.with_details(
+                    Some(*total_solver_time),
+                    *total_nodes,
+                    Some(*time_limit),
+                    *freq_nb_solutions,
+                )
+            },
+            SolveInformation::Timeout { time_limit, .. }
+            | SolveInformation::Memout { time_limit, .. } => {
+                row.with_details(None, None, Some(*time_limit), None)
+            }
+            SolveInformation::Crash { time_limit, .. } => {
+                row.with_details(None, None, *time_limit, None)
+            }
+        }
     }
 }
 
-pub async fn populate_db(conn: &mut Conn, store: ExperimentStore) {
-    let mut rows: Vec<DBRow> = vec![];
-    for (id, experiment) in store.experiments.into_iter() {
-        for (c_id, config) in experiment.configs.into_iter() {
-            for solve in config.solve_information.iter() {
-                rows.push(DBRow::from_solve(&id, &c_id, solve));
-            }
+/// Metrics of one level of a solve, a row of the `level_info` table.
+pub struct LevelRow {
+    pub level: String,
+    pub solver_time: Option<f64>,
+    pub nodes: Option<u64>,
+    pub cumulative_nb_solutions: Option<u64>,
+    pub nb_vars: Option<u64>,
+    pub nb_clauses: Option<u64>,
+    pub nb_learnt_clauses: Option<u64>,
+}
+
+impl LevelRow {
+    /// One row for every level that appears in any of the metrics.
+    pub fn from_level_info(level_info: &LevelInformation) -> Vec<Self> {
+        let mut levels = BTreeSet::new();
+        for m in [
+            &level_info.nodes,
+            &level_info.cumulative_nb_solutions,
+            &level_info.nb_vars,
+            &level_info.nb_clauses,
+            &level_info.nb_learnt_clauses,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            levels.extend(m.keys());
         }
+        if let Some(m) = &level_info.solver_time {
+            levels.extend(m.keys());
+        }
+        let get =
+            |m: &Option<HashMap<String, u64>>, l: &str| m.as_ref().and_then(|m| m.get(l).copied());
+        levels
+            .into_iter()
+            .map(|l| LevelRow {
+                level: l.clone(),
+                solver_time: level_info
+                    .solver_time
+                    .as_ref()
+                    .and_then(|m| m.get(l).copied()),
+                nodes: get(&level_info.nodes, l),
+                cumulative_nb_solutions: get(&level_info.cumulative_nb_solutions, l),
+                nb_vars: get(&level_info.nb_vars, l),
+                nb_clauses: get(&level_info.nb_clauses, l),
+                nb_learnt_clauses: get(&level_info.nb_learnt_clauses, l),
+            })
+            .collect()
     }
-    conn.exec_batch(
-        r"INSERT INTO experiments (exp_id, config_id, result_type, measured_time, nb_solutions, machine_info, memory_limit, seed)
-          VALUES (:exp_id, :config_id, :result_type, :measured_time, :nb_solutions, :machine_info, :memory_limit, :seed)",
-        rows.into_iter().map(|r| params! {
-            "exp_id" => r.exp_id,
-            "config_id" => r.config_id,
-            "result_type" => r.result_type,
-            "measured_time" => r.measured_time,
-            "nb_solutions" => r.nb_solutions,
-            "machine_info" => r.machine_info,
-            "memory_limit" => r.memory_limit,
-            "seed" => r.seed,
-        })
-    ).await.unwrap();
-}
 
-pub async fn check_mode(conn: &mut Conn, exp_id: &str, config_id: &str, mode: Mode, exact: bool) {
-    match mode {
-        Mode::NbSolutions => check_sol(conn, exp_id).await,
-        _ => {
-            if exact {
-                check_exact_time(conn, exp_id, config_id).await;
-            } else {
-                check_best_time(conn, exp_id).await;
+    /// Inverse of `from_level_info`. A metric no level has a value for is None.
+    pub fn to_level_info(rows: Vec<Self>) -> LevelInformation {
+        fn insert<T>(m: &mut Option<HashMap<String, T>>, level: &str, v: Option<T>) {
+            if let Some(v) = v {
+                m.get_or_insert_with(HashMap::new)
+                    .insert(level.to_string(), v);
             }
         }
+        let mut info = LevelInformation::default();
+        for r in rows {
+            insert(&mut info.solver_time, &r.level, r.solver_time);
+            insert(&mut info.nodes, &r.level, r.nodes);
+            insert(
+                &mut info.cumulative_nb_solutions,
+                &r.level,
+                r.cumulative_nb_solutions,
+            );
+            insert(&mut info.nb_vars, &r.level, r.nb_vars);
+            insert(&mut info.nb_clauses, &r.level, r.nb_clauses);
+            insert(&mut info.nb_learnt_clauses, &r.level, r.nb_learnt_clauses);
+        }
+        info
     }
 }
 
-async fn check_sol(conn: &mut Conn, exp_id: &str) {
-    let res = get_experiment_successful_results(conn, exp_id).await;
-    let mut nb_solutions: Option<u64> = None;
-    let mut support: u16 = 0;
-    for row in res.into_iter() {
-        if let Some(nb) = row.nb_solutions {
-            match nb_solutions {
-                Some(n) => {
-                    assert_eq!(n, nb);
-                    support += 1;
-                }
-                None => {
-                    nb_solutions = Some(nb);
-                    support = 1;
-                }
-            }
-        }
+/// Attaches `level_info` rows to the experiment rows they belong to.
+pub fn attach_levels(rows: Vec<(u64, DBRow)>, levels: Vec<(u64, LevelRow)>) -> Vec<DBRow> {
+    let mut by_row: HashMap<u64, Vec<LevelRow>> = HashMap::new();
+    for (id, l) in levels {
+        by_row.entry(id).or_default().push(l);
     }
-    match nb_solutions {
-        Some(nb) => println!("DB_NB_SOLS {} with support {}", nb, support),
-        None => println!("EMPTY"),
+    rows.into_iter()
+        .map(|(id, mut r)| {
+            if let Some(l) = by_row.remove(&id) {
+                r.level_info = Some(Box::new(LevelRow::to_level_info(l)));
+            }
+            r
+        })
+        .collect()
+}
+
+/// One row per solve of every config of every experiment in the store.
+pub fn store_rows(store: &ExperimentStore) -> Vec<DBRow> {
+    let mut rows: Vec<DBRow> = vec![];
+    for (id, experiment) in store.experiments.iter() {
+        rows.extend(experiment_rows(id, experiment));
     }
+    rows
 }
 
-async fn check_best_time(conn: &mut Conn, exp_id: &str) {
-    let res;
-    if exp_id.contains("rel_sub") {
-        let exps = get_rsd_extra_exp_ids(exp_id);
-        // cannot use streams here since conn is &mut
-        let mut in_res = vec![];
-        for e in exps {
-            in_res.extend(get_experiment_results(conn, &e).await);
+/// One row per solve of every config of an experiment.
+pub fn experiment_rows(exp_id: &str, experiment: &ExperimentMultiple) -> Vec<DBRow> {
+    let mut rows: Vec<DBRow> = vec![];
+    for (c_id, config) in experiment.configs.iter() {
+        for solve in config.solve_information.iter() {
+            rows.push(DBRow::from_solve(exp_id, c_id, solve));
         }
-        res = in_res;
-    } else {
-        res = get_experiment_results(conn, exp_id).await;
-    }
-    check_time(res);
-}
-
-fn get_rsd_extra_exp_ids(exp_id: &str) -> Vec<String> {
-    // for rsd different exp ids we need to add this extra exps for best
-    let mut naked_str = exp_id.replace("rel_sub", "");
-    naked_str = naked_str.replace("_complete", "");
-    naked_str = naked_str.replace("_par_neg", "");
-    naked_str = naked_str.replace("_par_pos", "");
-    let mut vec = vec![];
-    for i in 0..4 {
-        let mut base = String::from("rel_sub");
-        match i {
-            1 => base.push_str("_complete"),
-            2 => base.push_str("_par_neg"),
-            3 => base.push_str("_par_pos"),
-            _ => {}
+    }
+    rows
+}
+
+/// How `ExperimentTable::import` treats rows that are already in the table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportMode {
+    /// Inserts every row, even if the same run is already there.
+    Append,
+    /// Inserts only the runs whose `RunKey` is not in the table yet.
+    SkipDuplicates,
+    /// Deletes every row of an experiment before inserting its runs.
+    ReplacePerExperiment,
+}
+
+impl ImportMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "append" => Some(ImportMode::Append),
+            "skip-duplicates" => Some(ImportMode::SkipDuplicates),
+            "replace-per-experiment" => Some(ImportMode::ReplacePerExperiment),
+            _ => None,
         }
-        base.push_str(&naked_str);
-        vec.push(base);
     }
-    vec
 }
 
-async fn check_exact_time(conn: &mut Conn, exp_id: &str, config_id: &str) {
-    let res = get_experiment_config_results(conn, exp_id, config_id).await;
-    check_time(res);
+/// Identity of a run for `ImportMode::SkipDuplicates`: the same experiment,
+/// config, seed and machine. Seeds are compared bit for bit.
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct RunKey {
+    exp_id: String,
+    config_id: String,
+    seed: Option<u64>,
+    machine_info: String,
 }
 
-// I don't like this function. Want to rewrite it.
-fn check_time(res: Vec<DBRow>) {
-    let mut res_time: (Option<f64>, Option<&str>) = (None, None);
-    for row in res.iter() {
-        if let Some(result_type) = res_time.1 {
-            if row.result_type == "SUCCESS" {
-                if result_type == "SUCCESS" {
-                    match res_time.0 {
-                        Some(t) => {
-                            if row.measured_time < t {
-                                res_time = (Some(row.measured_time), Some("SUCCESS"))
-                            }
-                        }
-                        None => res_time = (Some(row.measured_time), Some("SUCCESS")),
-                    }
-                } else {
-                    res_time = (Some(row.measured_time), Some("SUCCESS"));
+impl RunKey {
+    pub fn new(exp_id: &str, config_id: &str, seed: Option<f64>, machine_info: &str) -> Self {
+        RunKey {
+            exp_id: exp_id.to_string(),
+            config_id: config_id.to_string(),
+            seed: seed.map(f64::to_bits),
+            machine_info: machine_info.to_string(),
+        }
+    }
+
+    pub fn of(row: &DBRow) -> Self {
+        RunKey::new(&row.exp_id, &row.config_id, row.seed, &row.machine_info)
+    }
+}
+
+/// Columns added to the original `experiments` table by the second migration
+/// so that a full `SolveInformation` can be stored. The types are understood by every
+/// supported database.
+pub const DETAIL_COLUMNS: [(&str, &str); 4] = [
+    ("solver_time", "DOUBLE PRECISION"),
+    ("total_nodes", "BIGINT"),
+    ("time_limit", "BIGINT"),
+    ("freq_nb_solutions", "BIGINT"),
+];
+
+/// Schema version written by this version of rrr, i.e. the number of
+/// migrations in `ExperimentTable::apply_migration`.
+pub const SCHEMA_VERSION: u64 = 3;
+
+/// The `experiments` table of a SQL database. Backends provide the dialect
+/// specific statements, loading a store or a single experiment is shared.
+pub trait ExperimentTable: ResultBackend {
+    /// Runs a statement without parameters or results.
+    fn execute(&mut self, sql: &str);
+
+    /// First column of the first row of a query, if it is a non null number.
+    fn query_number(&mut self, sql: &str) -> Option<i64>;
+
+    fn has_table(&mut self, table: &str) -> bool;
+
+    fn has_column(&mut self, table: &str, column: &str) -> bool;
+
+    fn has_index(&mut self, table: &str, index: &str) -> bool;
+
+    /// Creates the original `experiments` table, later migrations add the rest.
+    fn create_base_table(&mut self);
+
+    /// Inserts rows together with their level info.
+    fn insert_rows(&mut self, rows: Vec<DBRow>);
+
+    /// Every row of the table with its level info.
+    fn all_rows(&mut self) -> Vec<DBRow>;
+
+    /// Replaces every row of an experiment, with its level info, by `rows`
+    /// in a single transaction.
+    fn replace_experiment(&mut self, exp_id: &str, rows: Vec<DBRow>);
+
+    /// Keys of the rows of an experiment.
+    fn run_keys(&mut self, exp_id: &str) -> Vec<RunKey>;
+
+    fn drop_table(&mut self) {
+        self.execute("DROP TABLE IF EXISTS level_info");
+        self.execute("DROP TABLE IF EXISTS experiments");
+        self.execute("DROP TABLE IF EXISTS schema_version");
+    }
+
+    /// Current schema version of the db, 0 if there is no table at all.
+    /// Tables created before schema versioning are recognised by their columns.
+    fn schema_version(&mut self) -> u64 {
+        if self.has_table("schema_version") {
+            self.query_number("SELECT MAX(version) FROM schema_version")
+                .unwrap_or(0) as u64
+        } else if !self.has_table("experiments") {
+            0
+        } else if self.has_column("experiments", "solver_time") {
+            2
+        } else {
+            1
+        }
+    }
+
+    /// Brings the schema from `version - 1` to `version`. Every step checks
+    /// what is already there, so an interrupted migration can be re-run.
+    fn apply_migration(&mut self, version: u64) {
+        match version {
+            1 => {
+                if !self.has_table("experiments") {
+                    self.create_base_table();
                 }
-            } else if row.result_type == "TIMEOUT" && result_type == "TIMEOUT" {
-                match res_time.0 {
-                    Some(t) => {
-                        if row.measured_time > t {
-                            res_time = (Some(row.measured_time), Some("TIMEOUT"))
-                        }
+            }
+            2 => {
+                for (column, sql_type) in DETAIL_COLUMNS {
+                    if !self.has_column("experiments", column) {
+                        self.execute(&format!(
+                            "ALTER TABLE experiments ADD COLUMN {} {}",
+                            column, sql_type
+                        ));
                     }
-                    None => res_time = (Some(row.measured_time), Some("TIMEOUT")),
                 }
-            } else if row.result_type == "MEMOUT" && result_type == "MEMOUT" {
-                match res_time.0 {
-                    Some(t) => {
-                        if row.measured_time > t {
-                            res_time = (Some(row.measured_time), Some("MEMOUT"))
-                        }
-                    }
-                    None => res_time = (Some(row.measured_time), Some("MEMOUT")),
+                self.execute(
+                    r"CREATE TABLE IF NOT EXISTS level_info (
+                        experiment_id BIGINT NOT NULL,
+                        level VARCHAR(255) NOT NULL,
+                        solver_time DOUBLE PRECISION,
+                        nodes BIGINT,
+                        cumulative_nb_solutions BIGINT,
+                        nb_vars BIGINT,
+                        nb_clauses BIGINT,
+                        nb_learnt_clauses BIGINT,
+                        PRIMARY KEY (experiment_id, level)
+                      )
+                    ",
+                );
+            }
+            3 => {
+                if !self.has_index("experiments", "experiments_exp_config") {
+                    self.execute(
+                        "CREATE INDEX experiments_exp_config ON experiments (exp_id, config_id)",
+                    );
                 }
             }
+            _ => panic!("Unknown schema version {}", version),
+        }
+    }
+
+    /// Applies the missing migrations in order without touching existing rows.
+    /// Returns the version the db was at.
+    fn migrate(&mut self) -> u64 {
+        let from = self.schema_version();
+        assert!(
+            from <= SCHEMA_VERSION,
+            "The db schema version {} is newer than this rrr ({}).",
+            from,
+            SCHEMA_VERSION
+        );
+        self.execute("CREATE TABLE IF NOT EXISTS schema_version (version INTEGER NOT NULL)");
+        for version in from + 1..=SCHEMA_VERSION {
+            self.apply_migration(version);
+            self.execute("DELETE FROM schema_version");
+            self.execute(&format!(
+                "INSERT INTO schema_version (version) VALUES ({})",
+                version
+            ));
+        }
+        from
+    }
+
+    /// Errors unless the db is at the schema version of this rrr.
+    fn check_schema(&mut self) -> Result<(), String> {
+        let version = self.schema_version();
+        if version == SCHEMA_VERSION {
+            Ok(())
+        } else if version < SCHEMA_VERSION {
+            Err(format!(
+                "The db schema is at version {} but rrr needs version {}. Run `rrr remote migrate` first.",
+                version, SCHEMA_VERSION
+            ))
         } else {
-            res_time = (Some(row.measured_time), Some(row.result_type.as_str()));
+            Err(format!(
+                "The db schema is at version {} which is newer than this rrr ({}). Update rrr.",
+                version, SCHEMA_VERSION
+            ))
         }
     }
-    if let Some(res_str) = res_time.1 {
-        if res_str == "SUCCESS" {
-            match res_time.0 {
-                Some(t) => println!("DB_MIN_SR {}", t),
-                None => println!("EMPTY"),
-            }
-        } else if res_str == "TIMEOUT" {
-            match res_time.0 {
-                Some(t) => println!("DB_MAX_TIMEOUT {}", t),
-                None => println!("EMPTY"),
-            }
-        } else if res_str == "MEMOUT" {
-            match res_time.0 {
-                Some(t) => println!("DB_MAX_MEMOUT_TIME {}", t),
-                None => println!("EMPTY"),
+
+    /// Drops and recreates the experiments table.
+    fn init(&mut self) {
+        self.drop_table();
+        self.migrate();
+    }
+
+    /// Loads a store into the table. Returns the number of inserted rows and
+    /// the number of runs skipped as duplicates.
+    fn import(&mut self, store: &ExperimentStore, mode: ImportMode) -> (usize, usize) {
+        let mut rows = vec![];
+        let mut inserted = 0;
+        let mut skipped = 0;
+        for (exp_id, experiment) in store.experiments.iter() {
+            let exp_rows = experiment_rows(exp_id, experiment);
+            match mode {
+                ImportMode::Append => rows.extend(exp_rows),
+                ImportMode::ReplacePerExperiment => {
+                    // delete and insert together, a failure keeps the old rows
+                    inserted += exp_rows.len();
+                    self.replace_experiment(exp_id, exp_rows);
+                }
+                ImportMode::SkipDuplicates => {
+                    // also catches runs that appear twice in the store itself
+                    let mut known: HashSet<RunKey> = self.run_keys(exp_id).into_iter().collect();
+                    for r in exp_rows {
+                        if known.insert(RunKey::of(&r)) {
+                            rows.push(r);
+                        } else {
+                            skipped += 1;
+                        }
+                    }
+                }
             }
         }
-    } else {
-        println!("EMPTY");
+        inserted += rows.len();
+        self.insert_rows(rows);
+        (inserted, skipped)
+    }
+
+    fn commit(&mut self, exp: ExperimentSingle) {
+        assert_eq!(exp.config.solve_information.len(), 1);
+        let r = DBRow::from_solve(
+            &exp.exp_id,
+            &exp.config_id,
+            exp.config.solve_information.first().unwrap(),
+        );
+        self.insert_rows(vec![r]);
+    }
+
+    /// Rebuilds an experiment store from the whole table.
+    fn export(&mut self, split_ids: bool) -> ExperimentStore {
+        rows_to_store(self.all_rows(), split_ids)
     }
 }
 
-pub async fn check_nb_successful(conn: &mut Conn, exp_id: &str, config_id: &str) -> usize {
-    let res = get_experiment_config_successful_results(conn, exp_id, config_id).await;
-    let mut seed_vec = vec![];
-    for row in res.iter() {
-        if let Some(seed) = row.seed {
-            if !seed_vec.contains(&seed) {
-                seed_vec.push(seed);
-            }
+/// What `merge_rows` did with the rows it was given.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct RowMerge {
+    pub merged: usize,
+    /// Runs the store already has.
+    pub skipped: usize,
+    /// Runs of configs the store does not have, left out unless asked for.
+    pub new_configs: usize,
+    /// Rows written before the table was upgraded, see `DBRow::to_solve`.
+    pub incomplete: usize,
+}
+
+/// Merges rows into a store like side files, so a run with a different number
+/// of solutions is DOUBTED. Runs the store already has are skipped, as
+/// decided by `json::contains_run`. The db does not keep config metadata, so
+/// runs of configs the store does not have are left out unless
+/// `add_new_configs` is set, in which case these configs get empty metadata.
+pub fn merge_rows(
+    store: &mut ExperimentStore,
+    rows: Vec<DBRow>,
+    add_new_configs: bool,
+) -> RowMerge {
+    let mut counts = RowMerge::default();
+    for r in rows {
+        let Some(exp) = r.to_experiment() else {
+            counts.incomplete += 1;
+            continue;
+        };
+        if json::contains_run(store, &exp) {
+            counts.skipped += 1;
+            continue;
+        }
+        let known_config = store
+            .experiments
+            .get(&r.exp_id)
+            .is_some_and(|e| e.configs.contains_key(&r.config_id));
+        if !known_config && !add_new_configs {
+            counts.new_configs += 1;
+            continue;
         }
+        json::merge_one_experiment(store, exp);
+        counts.merged += 1;
+    }
+    counts
+}
+
+/// Groups rows by `exp_id` and `config_id` into a store. The table does not
+/// keep experiment and config metadata, so these fields are left empty, except
+/// that `split_ids` fills `model`, `instance` and `freq` from experiment ids
+/// of the form `model_instance_freq` (see `split_exp_id`). Rows written before
+/// the table was upgraded are left out with a warning.
+pub fn rows_to_store(rows: Vec<DBRow>, split_ids: bool) -> ExperimentStore {
+    let mut store = ExperimentStore {
+        experiments: HashMap::new(),
+    };
+    let mut incomplete = 0;
+    for r in rows {
+        let Some(solve) = r.to_solve() else {
+            incomplete += 1;
+            continue;
+        };
+        let experiment = store
+            .experiments
+            .entry(r.exp_id)
+            .or_insert_with_key(|exp_id| {
+                let (model, instance, freq) = if split_ids {
+                    split_exp_id(exp_id)
+                } else {
+                    (String::new(), String::new(), 0.0)
+                };
+                Box::new(ExperimentMultiple::new(
+                    model,
+                    instance,
+                    freq,
+                    HashMap::new(),
+                ))
+            });
+        experiment
+            .configs
+            .entry(r.config_id)
+            .or_default()
+            .solve_information
+            .push(solve);
+    }
+    if incomplete > 0 {
+        println!(
+            "Left out {} rows written before the table was upgraded, they lack the fields of a run.",
+            incomplete
+        );
+    }
+    store
+}
+
+/// Model, instance and frequency of a `model_instance_freq` experiment id,
+/// split at the first and last `_`. The frequency is 0 when the last part is
+/// not a number, and the instance empty when there is a single part.
+pub fn split_exp_id(exp_id: &str) -> (String, String, f64) {
+    let (rest, freq) = match exp_id.rsplit_once('_') {
+        Some((rest, f)) if f.parse::<f64>().is_ok() => (rest, f.parse().unwrap()),
+        _ => (exp_id, 0.0),
+    };
+    match rest.split_once('_') {
+        Some((model, instance)) => (model.to_string(), instance.to_string(), freq),
+        None => (rest.to_string(), String::new(), freq),
     }
-    seed_vec.len()
+}
+
+async fn insert_rows(conn: &mut Conn, rows: Vec<DBRow>) {
+    let mut tx = conn.start_transaction(TxOpts::default()).await.unwrap();
+    insert_into(&mut tx, rows).await;
+    tx.commit().await.unwrap();
+}
+
+/// Inserts rows together with their level info in an open transaction.
+async fn insert_into(tx: &mut Transaction<'_>, rows: Vec<DBRow>) {
+    for r in rows {
+        tx.exec_drop(
+            r"INSERT INTO experiments (exp_id, config_id, result_type, measured_time, nb_solutions, machine_info, memory_limit, seed, solver_time, total_nodes, time_limit, freq_nb_solutions)
+              VALUES (:exp_id, :config_id, :result_type, :measured_time, :nb_solutions, :machine_info, :memory_limit, :seed, :solver_time, :total_nodes, :time_limit, :freq_nb_solutions)",
+            params! {
+                "exp_id" => r.exp_id,
+                "config_id" => r.config_id,
+                "result_type" => r.result_type,
+                "measured_time" => r.measured_time,
+                "nb_solutions" => r.nb_solutions,
+                "machine_info" => r.machine_info,
+                "memory_limit" => r.memory_limit,
+                "seed" => r.seed,
+                "solver_time" => r.solver_time,
+                "total_nodes" => r.total_nodes,
+                "time_limit" => r.time_limit,
+                "freq_nb_solutions" => r.freq_nb_solutions,
+            },
+        )
+        .await
+        .unwrap();
+        if let Some(level_info) = r.level_info {
+            let id = tx.last_insert_id().unwrap();
+            tx.exec_batch(
+                r"INSERT INTO level_info (experiment_id, level, solver_time, nodes, cumulative_nb_solutions, nb_vars, nb_clauses, nb_learnt_clauses)
+                  VALUES (:experiment_id, :level, :solver_time, :nodes, :cumulative_nb_solutions, :nb_vars, :nb_clauses, :nb_learnt_clauses)",
+                LevelRow::from_level_info(&level_info).into_iter().map(|l| params! {
+                    "experiment_id" => id,
+                    "level" => l.level,
+                    "solver_time" => l.solver_time,
+                    "nodes" => l.nodes,
+                    "cumulative_nb_solutions" => l.cumulative_nb_solutions,
+                    "nb_vars" => l.nb_vars,
+                    "nb_clauses" => l.nb_clauses,
+                    "nb_learnt_clauses" => l.nb_learnt_clauses,
+                }),
+            )
+            .await
+            .unwrap();
+        }
+    }
+}
+
+async fn get_all_results(conn: &mut Conn) -> Vec<DBRow> {
+    let rows: Vec<Row> = conn
+        .query("SELECT id, exp_id, config_id, result_type, measured_time, nb_solutions, memory_limit, machine_info, seed, solver_time, total_nodes, time_limit, freq_nb_solutions from experiments")
+        .await
+        .unwrap();
+    let rows = rows
+        .into_iter()
+        .map(|mut row| {
+            let r = DBRow::new(
+                row.take(1).unwrap(),
+                row.take(2).unwrap(),
+                row.take(3).unwrap(),
+                row.take(4).unwrap(),
+                row.take(5).unwrap(),
+                row.take(7).unwrap(),
+                row.take(6).unwrap(),
+                row.take(8).unwrap(),
+            )
+            .with_details(
+                row.take(9).unwrap(),
+                row.take(10).unwrap(),
+                row.take(11).unwrap(),
+                row.take(12).unwrap(),
+            );
+            (row.take(0).unwrap(), r)
+        })
+        .collect();
+    let levels = conn
+        .query_map(
+            "SELECT experiment_id, level, solver_time, nodes, cumulative_nb_solutions, nb_vars, nb_clauses, nb_learnt_clauses from level_info",
+            |(id, level, solver_time, nodes, cumulative_nb_solutions, nb_vars, nb_clauses, nb_learnt_clauses)| {
+                (
+                    id,
+                    LevelRow {
+                        level,
+                        solver_time,
+                        nodes,
+                        cumulative_nb_solutions,
+                        nb_vars,
+                        nb_clauses,
+                        nb_learnt_clauses,
+                    },
+                )
+            },
+        )
+        .await
+        .unwrap();
+    attach_levels(rows, levels)
 }
 
 async fn get_experiment_results(conn: &mut Conn, exp_id: &str) -> Vec<DBRow> {
     conn.exec_map(
-        "SELECT config_id, result_type, measured_time, nb_solutions, memory_limit, machine_info, seed from experiments WHERE exp_id = ?",
+        "SELECT config_id, result_type, measured_time, nb_solutions, memory_limit, machine_info, seed, solver_time from experiments WHERE exp_id = ?",
         (exp_id, ),
-        |(config_id, result_type, measured_time, nb_solutions, memory_limit, machine_info, seed)| {
+        |(config_id, result_type, measured_time, nb_solutions, memory_limit, machine_info, seed, solver_time)| {
             DBRow::new(
                 exp_id.to_string(),
                 config_id,
@@ -418,27 +1007,40 @@ async fn get_experiment_results(conn: &mut Conn, exp_id: &str) -> Vec<DBRow> {
                 memory_limit,
                 seed,
             )
+            .with_details(solver_time, None, None, None)
         },
     ).await.unwrap()
 }
 
-async fn get_experiment_successful_results(conn: &mut Conn, exp_id: &str) -> Vec<DBRow> {
-    conn.exec_map(
-        "SELECT config_id, measured_time, nb_solutions, memory_limit, machine_info, seed from experiments WHERE exp_id = ? and result_type = ?",
-        (exp_id, "SUCCESS"),
-        |(config_id,  measured_time, nb_solutions, memory_limit, machine_info, seed)| {
+/// Most experiment ids bound in one `IN` list, well below the placeholder
+/// limit of MySQL.
+const BATCH_SIZE: usize = 10_000;
+
+/// Runs of many experiments, with one query per `BATCH_SIZE` ids.
+async fn get_experiments_results(conn: &mut Conn, exp_ids: &[String]) -> Vec<DBRow> {
+    let mut rows = vec![];
+    for chunk in exp_ids.chunks(BATCH_SIZE) {
+        let placeholders = vec!["?"; chunk.len()].join(", ");
+        let sql = format!(
+            "SELECT exp_id, config_id, result_type, measured_time, nb_solutions, memory_limit, machine_info, seed, solver_time from experiments WHERE exp_id IN ({})",
+            placeholders
+        );
+        let chunk_rows: Vec<Row> = conn.exec(sql, chunk.to_vec()).await.unwrap();
+        rows.extend(chunk_rows.into_iter().map(|mut row| {
             DBRow::new(
-                exp_id.to_string(),
-                config_id,
-                "SUCCESS".to_string(),
-                measured_time,
-                nb_solutions,
-                machine_info,
-                memory_limit,
-                seed,
+                row.take(0).unwrap(),
+                row.take(1).unwrap(),
+                row.take(2).unwrap(),
+                row.take(3).unwrap(),
+                row.take(4).unwrap(),
+                row.take(6).unwrap(),
+                row.take(5).unwrap(),
+                row.take(7).unwrap(),
             )
-        },
-    ).await.unwrap()
+            .with_details(row.take(8).unwrap(), None, None, None)
+        }));
+    }
+    rows
 }
 
 async fn get_experiment_config_results(
@@ -447,9 +1049,9 @@ async fn get_experiment_config_results(
     config_id: &str,
 ) -> Vec<DBRow> {
     conn.exec_map(
-        "SELECT result_type, measured_time, nb_solutions, memory_limit, machine_info, seed from experiments WHERE exp_id = ? and config_id = ?",
+        "SELECT result_type, measured_time, nb_solutions, memory_limit, machine_info, seed, solver_time from experiments WHERE exp_id = ? and config_id = ?",
         (exp_id, config_id),
-        |(result_type, measured_time, nb_solutions, memory_limit, machine_info, seed)| {
+        |(result_type, measured_time, nb_solutions, memory_limit, machine_info, seed, solver_time)| {
             DBRow::new(
                 exp_id.to_string(),
                 config_id.to_string(),
@@ -460,55 +1062,412 @@ async fn get_experiment_config_results(
                 memory_limit,
                 seed,
             )
+            .with_details(solver_time, None, None, None)
         },
     ).await.unwrap()
 }
 
-async fn get_experiment_config_successful_results(
-    conn: &mut Conn,
-    exp_id: &str,
-    config_id: &str,
-) -> Vec<DBRow> {
+async fn replace_experiment(conn: &mut Conn, exp_id: &str, rows: Vec<DBRow>) {
+    let mut tx = conn.start_transaction(TxOpts::default()).await.unwrap();
+    tx.exec_drop(
+        "DELETE FROM level_info WHERE experiment_id IN (SELECT id FROM experiments WHERE exp_id = ?)",
+        (exp_id,),
+    )
+    .await
+    .unwrap();
+    tx.exec_drop("DELETE FROM experiments WHERE exp_id = ?", (exp_id,))
+        .await
+        .unwrap();
+    insert_into(&mut tx, rows).await;
+    tx.commit().await.unwrap();
+}
+
+async fn get_run_keys(conn: &mut Conn, exp_id: &str) -> Vec<RunKey> {
     conn.exec_map(
-        "SELECT measured_time, nb_solutions, memory_limit, machine_info, seed from experiments WHERE exp_id = ? and config_id = ? and result_type = ?",
-        (exp_id, config_id, "SUCCESS"),
-        |(measured_time, nb_solutions, memory_limit, machine_info, seed)| {
-            DBRow::new(
-                exp_id.to_string(),
-                config_id.to_string(),
-                "SUCCESS".to_string(),
-                measured_time,
-                nb_solutions,
-                machine_info,
-                memory_limit,
-                seed,
-            )
+        "SELECT config_id, seed, machine_info from experiments WHERE exp_id = ?",
+        (exp_id,),
+        |(config_id, seed, machine_info): (String, Option<f64>, String)| {
+            RunKey::new(exp_id, &config_id, seed, &machine_info)
         },
-    ).await.unwrap()
+    )
+    .await
+    .unwrap()
 }
 
-pub async fn commit_to_db(conn: &mut Conn, exp: ExperimentSingle) {
-    let exp_id = exp.exp_id;
-    let config_id = exp.config_id;
-    assert_eq!(exp.config.solve_information.len(), 1);
-    // I can consume the sol_info vec and move instead of borrow since sol info is not required later.
-    let r = DBRow::from_solve(
-        &exp_id,
-        &config_id,
-        exp.config.solve_information.get(0).unwrap(),
-    );
-    conn.exec_drop(
-        r"INSERT INTO experiments (exp_id, config_id, result_type, measured_time, nb_solutions, machine_info, memory_limit, seed)
-          VALUES (:exp_id, :config_id, :result_type, :measured_time, :nb_solutions, :machine_info, :memory_limit, :seed)",
-         params! {
-            "exp_id" => r.exp_id,
-            "config_id" => r.config_id,
-            "result_type" => r.result_type,
-            "measured_time" => r.measured_time,
-            "nb_solutions" => r.nb_solutions,
-            "machine_info" => r.machine_info,
-            "memory_limit" => r.memory_limit,
-            "seed" => r.seed,
+impl From<DBRow> for Run {
+    fn from(row: DBRow) -> Self {
+        Run {
+            status: RunStatus::from_name(&row.result_type).expect("Unknown result_type in db"),
+            config_id: row.config_id,
+            sr_time: row.measured_time,
+            // rows from before the table upgrade have no solver time
+            solver_time: row.solver_time,
+            nb_solutions: row.nb_solutions,
+            seed: row.seed,
         }
-    ).await.unwrap();
+    }
+}
+
+/// Blocking handle on the remote MySQL `experiments` table.
+pub struct MySqlBackend {
+    rt: Runtime,
+    conn: Conn,
+}
+
+impl MySqlBackend {
+    pub fn connect(db: DBConfig<'_>) -> Self {
+        let rt = Runtime::new().unwrap();
+        let conn = rt.block_on(init_db_connection(db));
+        MySqlBackend { rt, conn }
+    }
+}
+
+impl ExperimentTable for MySqlBackend {
+    fn execute(&mut self, sql: &str) {
+        self.rt.block_on(self.conn.query_drop(sql)).unwrap();
+    }
+
+    fn query_number(&mut self, sql: &str) -> Option<i64> {
+        let value: Option<Option<i64>> = self.rt.block_on(self.conn.query_first(sql)).unwrap();
+        value.flatten()
+    }
+
+    fn has_table(&mut self, table: &str) -> bool {
+        self.rt.block_on(has_table(&mut self.conn, table))
+    }
+
+    fn has_column(&mut self, table: &str, column: &str) -> bool {
+        self.rt.block_on(has_column(&mut self.conn, table, column))
+    }
+
+    fn has_index(&mut self, table: &str, index: &str) -> bool {
+        self.rt.block_on(has_index(&mut self.conn, table, index))
+    }
+
+    fn create_base_table(&mut self) {
+        self.rt.block_on(create_base_table(&mut self.conn));
+    }
+
+    fn insert_rows(&mut self, rows: Vec<DBRow>) {
+        self.rt.block_on(insert_rows(&mut self.conn, rows));
+    }
+
+    fn all_rows(&mut self) -> Vec<DBRow> {
+        self.rt.block_on(get_all_results(&mut self.conn))
+    }
+
+    fn replace_experiment(&mut self, exp_id: &str, rows: Vec<DBRow>) {
+        self.rt
+            .block_on(replace_experiment(&mut self.conn, exp_id, rows));
+    }
+
+    fn run_keys(&mut self, exp_id: &str) -> Vec<RunKey> {
+        self.rt.block_on(get_run_keys(&mut self.conn, exp_id))
+    }
+}
+
+impl ResultBackend for MySqlBackend {
+    fn experiment_runs(&mut self, exp_id: &str) -> Vec<Run> {
+        let rows = self
+            .rt
+            .block_on(get_experiment_results(&mut self.conn, exp_id));
+        rows.into_iter().map(Run::from).collect()
+    }
+
+    fn config_runs(&mut self, exp_id: &str, config_id: &str) -> Vec<Run> {
+        let rows = self.rt.block_on(get_experiment_config_results(
+            &mut self.conn,
+            exp_id,
+            config_id,
+        ));
+        rows.into_iter().map(Run::from).collect()
+    }
+
+    fn batch_runs(&mut self, exp_ids: &[String]) -> RunCache {
+        let rows = self
+            .rt
+            .block_on(get_experiments_results(&mut self.conn, exp_ids));
+        RunCache::new(rows.into_iter().map(|r| (r.exp_id.clone(), Run::from(r))))
+    }
+
+    fn answer_all(&mut self, queries: &[Query]) -> Vec<QueryRecord> {
+        self.batch_runs(&batch_exp_ids(queries)).answer_all(queries)
+    }
+}
+
+/// Checks every `ExperimentTable` backend has to pass, run by the tests of
+/// each backend on an empty db.
+#[cfg(test)]
+pub mod table_tests {
+    use super::*;
+    use crate::utils::Mode;
+
+    const STORE: &str = r#"{"experiments": {
+        "mA_i1_0.1": {"model": "mA", "instance": "i1", "freq": 0.1, "configs": {
+            "c_kissat": {"preprocess": "None", "representation": "rep1", "solver": "kissat", "incomparability": false, "interactive": false, "native": true, "compressed": false, "ordered": false, "no_solution_blocking": false, "mdd": false, "cgroups": true, "solve_information": [
+                {"type": "SUCCESS", "total_solver_time": 8.0, "total_sr_time": 10.0, "total_nodes": 100, "nb_solutions": 5, "seed": 1.0, "memory_limit": 4096, "time_limit": 3600, "machine_info": "m1", "freq_nb_solutions": 2,
+                 "level_info": {"solver_time": {"1": 5.0, "2": 3.0}, "nodes": {"1": 50, "2": 50}, "cumulative_nb_solutions": {"1": 2, "2": 5}, "nb_vars": {"1": 10}, "nb_clauses": {"1": 20}, "nb_learnt_clauses": {"2": 7}}},
+                {"type": "TIMEOUT", "seed": 2.0, "memory_limit": 4096, "time_limit": 3600, "machine_info": "m1"}]},
+            "c_cplex": {"preprocess": "None", "representation": "rep1", "solver": "cplex", "incomparability": false, "interactive": false, "native": true, "compressed": false, "ordered": false, "no_solution_blocking": false, "mdd": false, "cgroups": true, "solve_information": [
+                {"type": "MEMOUT", "seed": 1.0, "memory_limit": 4096, "time_limit": 3600, "machine_info": "m2", "crash_time": 12.5}]}}},
+        "mB_i2_0.5": {"model": "mB", "instance": "i2", "freq": 0.5, "configs": {
+            "c_kissat": {"preprocess": "None", "representation": "rep1", "solver": "kissat", "incomparability": false, "interactive": false, "native": true, "compressed": false, "ordered": false, "no_solution_blocking": false, "mdd": false, "cgroups": true, "solve_information": [
+                {"type": "CRASHED", "seed": null, "memory_limit": 2048, "time_limit": null, "machine_info": "m3", "crash_time": 3.0},
+                {"type": "DOUBTED", "total_solver_time": 1.5, "total_sr_time": 2.0, "total_nodes": null, "nb_solutions": 7, "seed": 3.0, "memory_limit": 2048, "time_limit": 60, "machine_info": "m3",
+                 "level_info": {"solver_time": null, "nodes": {"1": 4}, "cumulative_nb_solutions": null, "nb_vars": null, "nb_clauses": null, "nb_learnt_clauses": null}}]}}}
+    }}"#;
+
+    pub fn sample_store() -> ExperimentStore {
+        serde_json::from_str(STORE).unwrap()
+    }
+
+    fn nb_rows<T: ExperimentTable>(table: &mut T) -> i64 {
+        table
+            .query_number("SELECT COUNT(*) FROM experiments")
+            .unwrap()
+    }
+
+    /// Runs of every config as json, to compare stores whatever their
+    /// config metadata.
+    pub fn runs_json(store: &ExperimentStore) -> HashMap<(String, String), serde_json::Value> {
+        let mut runs = HashMap::new();
+        for (exp_id, e) in &store.experiments {
+            for (config_id, c) in &e.configs {
+                let solves = serde_json::to_value(&c.solve_information).unwrap();
+                runs.insert((exp_id.clone(), config_id.clone()), solves);
+            }
+        }
+        runs
+    }
+
+    pub fn init_creates_the_current_schema<T: ExperimentTable>(table: &mut T) {
+        table.init();
+        assert_eq!(table.schema_version(), SCHEMA_VERSION);
+        assert_eq!(table.check_schema(), Ok(()));
+        assert!(table.has_table("level_info"));
+        assert!(table.has_index("experiments", "experiments_exp_config"));
+        assert_eq!(nb_rows(table), 0);
+
+        table.execute("INSERT INTO schema_version (version) VALUES (99)");
+        assert!(table
+            .check_schema()
+            .unwrap_err()
+            .contains("newer than this rrr"));
+        table.init();
+        assert_eq!(table.schema_version(), SCHEMA_VERSION);
+    }
+
+    pub fn migrate_keeps_rows_of_the_base_table<T: ExperimentTable>(table: &mut T) {
+        table.drop_table();
+        table.create_base_table();
+        table.execute(
+            "INSERT INTO experiments (exp_id, config_id, result_type, measured_time, nb_solutions, machine_info, memory_limit, seed) VALUES ('mA_i1_0.1', 'c_kissat', 'TIMEOUT', 3600, NULL, 'm1', 4096, 1)",
+        );
+        assert_eq!(table.schema_version(), 1);
+        assert!(table
+            .check_schema()
+            .unwrap_err()
+            .contains("Run `rrr remote migrate` first"));
+
+        assert_eq!(table.migrate(), 1);
+        assert_eq!(table.check_schema(), Ok(()));
+        // a second run has nothing left to do
+        assert_eq!(table.migrate(), SCHEMA_VERSION);
+        let rows = table.all_rows();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].time_limit, None);
+        // no run can be rebuilt without its time limit
+        assert!(rows[0].to_solve().is_none());
+        assert!(table.export(false).experiments.is_empty());
+    }
+
+    pub fn import_modes<T: ExperimentTable>(table: &mut T) {
+        let store = sample_store();
+        table.init();
+        assert_eq!(table.import(&store, ImportMode::Append), (5, 0));
+        assert_eq!(table.import(&store, ImportMode::Append), (5, 0));
+        assert_eq!(nb_rows(table), 10);
+
+        table.init();
+        assert_eq!(table.import(&store, ImportMode::SkipDuplicates), (5, 0));
+        assert_eq!(table.import(&store, ImportMode::SkipDuplicates), (0, 5));
+        assert_eq!(nb_rows(table), 5);
+
+        // mA keeps only its cplex run, mB is left alone
+        let mut replacement = sample_store();
+        replacement.experiments.remove("mB_i2_0.5");
+        let e = replacement.experiments.get_mut("mA_i1_0.1").unwrap();
+        e.configs.remove("c_kissat");
+        assert_eq!(
+            table.import(&replacement, ImportMode::ReplacePerExperiment),
+            (1, 0)
+        );
+        assert_eq!(nb_rows(table), 3);
+        let exported = runs_json(&table.export(false));
+        let expected = runs_json(&store);
+        assert!(!exported.contains_key(&("mA_i1_0.1".to_string(), "c_kissat".to_string())));
+        for key in [("mA_i1_0.1", "c_cplex"), ("mB_i2_0.5", "c_kissat")] {
+            let key = (key.0.to_string(), key.1.to_string());
+            assert_eq!(exported[&key], expected[&key]);
+        }
+        // level info of the replaced rows is gone, other rows keep theirs
+        assert_eq!(
+            table.query_number("SELECT COUNT(*) FROM level_info"),
+            Some(1)
+        );
+    }
+
+    pub fn export_round_trips_runs_and_level_info<T: ExperimentTable>(table: &mut T) {
+        let store = sample_store();
+        table.init();
+        table.import(&store, ImportMode::Append);
+        assert_eq!(runs_json(&table.export(false)), runs_json(&store));
+    }
+
+    pub fn commit_adds_one_run<T: ExperimentTable>(table: &mut T) {
+        table.init();
+        let row = DBRow::new(
+            "mC_i3_1".to_string(),
+            "c_kissat".to_string(),
+            "SUCCESS".to_string(),
+            4.0,
+            Some(3),
+            "m4".to_string(),
+            1024,
+            Some(0.25),
+        )
+        .with_details(Some(3.5), Some(9), Some(60), None);
+        let exp = row.to_experiment().unwrap();
+        let solve = serde_json::to_value(&exp.config.solve_information).unwrap();
+        table.commit(exp);
+        let exported = table.export(false);
+        let c = &exported.experiments["mC_i3_1"].configs["c_kissat"];
+        assert_eq!(serde_json::to_value(&c.solve_information).unwrap(), solve);
+        assert_eq!(table.run_keys("mC_i3_1"), vec![RunKey::of(&row)]);
+    }
+
+    pub fn batch_runs_answer_like_single_queries<T: ExperimentTable>(table: &mut T) {
+        let mut store = sample_store();
+        table.init();
+        table.import(&store, ImportMode::Append);
+        let ids = vec![
+            "mA_i1_0.1".to_string(),
+            "missing_i_0.1".to_string(),
+            "mB_i2_0.5".to_string(),
+        ];
+        let mut cache = table.batch_runs(&ids);
+        for id in &ids {
+            assert_eq!(
+                cache.experiment_runs(id).len(),
+                store.experiment_runs(id).len()
+            );
+        }
+        assert!(cache.experiment_runs("unasked_i_0.1").is_empty());
+
+        let mut queries = vec![];
+        for exp_id in ["mA_i1_0.1", "mB_i2_0.5", "missing_i_0.1"] {
+            let exp_id = exp_id.to_string();
+            for mode in [Mode::SRTime, Mode::SolverTime, Mode::NbSolutions] {
+                queries.push(Query::BestTime {
+                    exp_id: exp_id.clone(),
+                    mode,
+                });
+                for config_id in ["c_kissat", "c_cplex"] {
+                    queries.push(Query::Time {
+                        exp_id: exp_id.clone(),
+                        config_id: config_id.to_string(),
+                        mode,
+                    });
+                }
+            }
+            queries.push(Query::Sol {
+                exp_id: exp_id.clone(),
+            });
+            queries.push(Query::NbSuccess {
+                exp_id,
+                config_id: "c_kissat".to_string(),
+            });
+        }
+        let expected: Vec<QueryRecord> = queries.iter().map(|q| store.answer(q)).collect();
+        assert_eq!(table.answer_all(&queries), expected);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A run of a config only the db has.
+    fn new_config_run() -> DBRow {
+        DBRow::new(
+            "mA_i1_0.1".to_string(),
+            "c_new".to_string(),
+            "TIMEOUT".to_string(),
+            3600.0,
+            None,
+            "m9".to_string(),
+            4096,
+            Some(1.0),
+        )
+        .with_details(None, None, Some(3600), None)
+    }
+
+    #[test]
+    fn merge_rows_skips_known_runs_and_repeated_rows() {
+        let mut store = table_tests::sample_store();
+        let mut rows = store_rows(&store);
+        rows.push(new_config_run());
+        rows.push(new_config_run());
+        let counts = merge_rows(&mut store, rows, true);
+        assert_eq!(
+            counts,
+            RowMerge {
+                merged: 1,
+                skipped: 6,
+                ..Default::default()
+            }
+        );
+        let c = &store.experiments["mA_i1_0.1"].configs["c_new"];
+        assert_eq!(c.solve_information.len(), 1);
+        assert_eq!(c.solver, "");
+    }
+
+    #[test]
+    fn merge_rows_leaves_out_new_configs_and_incomplete_rows() {
+        let mut store = table_tests::sample_store();
+        let mut old_row = new_config_run();
+        old_row.config_id = "c_kissat".to_string();
+        old_row.time_limit = None;
+        let counts = merge_rows(&mut store, vec![new_config_run(), old_row], false);
+        assert_eq!(
+            counts,
+            RowMerge {
+                new_configs: 1,
+                incomplete: 1,
+                ..Default::default()
+            }
+        );
+        assert!(!store.experiments["mA_i1_0.1"].configs.contains_key("c_new"));
+    }
+
+    #[test]
+    fn rows_to_store_only_splits_ids_when_asked() {
+        let rows = || store_rows(&table_tests::sample_store());
+        let exported = rows_to_store(rows(), false);
+        let e = &exported.experiments["mA_i1_0.1"];
+        assert_eq!(
+            (e.model.as_str(), e.instance.as_str(), e.freq),
+            ("", "", 0.0)
+        );
+        let exported = rows_to_store(rows(), true);
+        let e = &exported.experiments["mA_i1_0.1"];
+        assert_eq!(
+            (e.model.as_str(), e.instance.as_str(), e.freq),
+            ("mA", "i1", 0.1)
+        );
+        assert_eq!(
+            split_exp_id("model_x"),
+            ("model".to_string(), "x".to_string(), 0.0)
+        );
+    }
 }
@@ -0,0 +1,911 @@
+//! Filter expressions selecting experiments and configs, e.g.
+//! `model == "x" && solver =~ "kissat" && !cgroups`.
+//!
+//! A filter is evaluated on one config of one experiment, or on one of its
+//! runs when it uses run fields like `status` or `machine_info`. Strings
+//! compare with `==`, `!=` and the regex matches `=~`, `!~`; numbers with
+//! `==`, `!=`, `<`, `<=`, `>`, `>=`; boolean flags alone, negated with `!` or
+//! compared to `true`/`false`. `&&`, `||`, `!` and parentheses combine them.
+//! A comparison on a field the source does not have, like the config
+//! metadata of db runs, is unknown rather than false: `!` keeps it unknown,
+//! `&&` and `||` only decide when the other side does, and a filter matches
+//! only when it is known to hold.
+
+use regex::Regex;
+
+use crate::backend::{batch_exp_ids, Query, QueryRecord, ResultBackend, Run, RunCache, RunStatus};
+use crate::db::{split_exp_id, DBRow};
+use crate::json::{
+    ConfigMultiple, ExperimentMultiple, ExperimentSingle, ExperimentStore, SolveInformation,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Str,
+    Num,
+    Bool,
+}
+
+/// Where a field comes from: the experiment and config ids, the config
+/// metadata, which the db does not keep, or a single run.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Scope {
+    Ids,
+    Config,
+    Run,
+}
+
+/// Fields a filter can use with their kind and scope.
+const FIELDS: &[(&str, Kind, Scope)] = &[
+    ("exp_id", Kind::Str, Scope::Ids),
+    ("config_id", Kind::Str, Scope::Ids),
+    ("model", Kind::Str, Scope::Ids),
+    ("instance", Kind::Str, Scope::Ids),
+    ("freq", Kind::Num, Scope::Ids),
+    ("preprocess", Kind::Str, Scope::Config),
+    ("representation", Kind::Str, Scope::Config),
+    ("solver", Kind::Str, Scope::Config),
+    ("incomparability", Kind::Bool, Scope::Config),
+    ("interactive", Kind::Bool, Scope::Config),
+    ("native", Kind::Bool, Scope::Config),
+    ("compressed", Kind::Bool, Scope::Config),
+    ("ordered", Kind::Bool, Scope::Config),
+    ("no_solution_blocking", Kind::Bool, Scope::Config),
+    ("mdd", Kind::Bool, Scope::Config),
+    ("cgroups", Kind::Bool, Scope::Config),
+    ("status", Kind::Str, Scope::Run),
+    ("machine_info", Kind::Str, Scope::Run),
+    ("seed", Kind::Num, Scope::Run),
+    ("memory_limit", Kind::Num, Scope::Run),
+    ("time_limit", Kind::Num, Scope::Run),
+];
+
+fn field_kind(name: &str) -> Option<(Kind, Scope)> {
+    FIELDS
+        .iter()
+        .find(|(n, _, _)| *n == name)
+        .map(|(_, kind, scope)| (*kind, *scope))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Str(String),
+    Num(f64),
+    Bool(bool),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Eq,
+    Ne,
+    Match,
+    NotMatch,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Op {
+    fn symbol(&self) -> &'static str {
+        match self {
+            Op::Eq => "==",
+            Op::Ne => "!=",
+            Op::Match => "=~",
+            Op::NotMatch => "!~",
+            Op::Lt => "<",
+            Op::Le => "<=",
+            Op::Gt => ">",
+            Op::Ge => ">=",
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Flag(String),
+    Compare {
+        field: String,
+        op: Op,
+        value: Value,
+        regex: Option<Regex>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Num(f64),
+    Op(Op),
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = vec![];
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        let (token, len) = match (c, next) {
+            (c, _) if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            ('&', Some('&')) => (Token::And, 2),
+            ('|', Some('|')) => (Token::Or, 2),
+            ('=', Some('=')) => (Token::Op(Op::Eq), 2),
+            ('!', Some('=')) => (Token::Op(Op::Ne), 2),
+            ('=', Some('~')) => (Token::Op(Op::Match), 2),
+            ('!', Some('~')) => (Token::Op(Op::NotMatch), 2),
+            ('<', Some('=')) => (Token::Op(Op::Le), 2),
+            ('>', Some('=')) => (Token::Op(Op::Ge), 2),
+            ('<', _) => (Token::Op(Op::Lt), 1),
+            ('>', _) => (Token::Op(Op::Gt), 1),
+            ('!', _) => (Token::Not, 1),
+            ('(', _) => (Token::Open, 1),
+            (')', _) => (Token::Close, 1),
+            ('"', _) => {
+                let mut s = String::new();
+                let mut j = i + 1;
+                loop {
+                    match chars.get(j) {
+                        None => return Err("Unterminated string in filter".to_string()),
+                        Some('"') => break,
+                        Some('\\') if j + 1 < chars.len() => {
+                            s.push(chars[j + 1]);
+                            j += 2;
+                        }
+                        Some(c) => {
+                            s.push(*c);
+                            j += 1;
+                        }
+                    }
+                }
+                (Token::Str(s), j + 1 - i)
+            }
+            (c, _) if c.is_ascii_digit() || c == '-' || c == '.' => {
+                let len = chars[i..]
+                    .iter()
+                    .take_while(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '+'))
+                    .count();
+                let text: String = chars[i..i + len].iter().collect();
+                let n = text
+                    .parse()
+                    .map_err(|_| format!("Bad number `{}` in filter", text))?;
+                (Token::Num(n), len)
+            }
+            (c, _) if c.is_alphabetic() || c == '_' => {
+                let len = chars[i..]
+                    .iter()
+                    .take_while(|c| c.is_alphanumeric() || **c == '_')
+                    .count();
+                (Token::Ident(chars[i..i + len].iter().collect()), len)
+            }
+            (c, _) => return Err(format!("Unexpected `{}` in filter", c)),
+        };
+        tokens.push(token);
+        i += len;
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut expr = self.and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.next();
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut expr = self.unary()?;
+        while self.peek() == Some(&Token::And) {
+            self.next();
+            expr = Expr::And(Box::new(expr), Box::new(self.unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        match self.next() {
+            Some(Token::Not) => Ok(Expr::Not(Box::new(self.unary()?))),
+            Some(Token::Open) => {
+                let expr = self.or()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(expr),
+                    _ => Err("Missing `)` in filter".to_string()),
+                }
+            }
+            Some(Token::Ident(field)) => self.comparison(field),
+            Some(t) => Err(format!("Expected a field in filter, found {:?}", t)),
+            None => Err("Filter ends too early".to_string()),
+        }
+    }
+
+    fn comparison(&mut self, field: String) -> Result<Expr, String> {
+        let Some((kind, _)) = field_kind(&field) else {
+            let known: Vec<&str> = FIELDS.iter().map(|(n, _, _)| *n).collect();
+            return Err(format!(
+                "Unknown field `{}` in filter, known fields: {}",
+                field,
+                known.join(", ")
+            ));
+        };
+        let op = match self.peek() {
+            Some(Token::Op(op)) => *op,
+            _ if kind == Kind::Bool => return Ok(Expr::Flag(field)),
+            _ => return Err(format!("`{}` needs to be compared to a value", field)),
+        };
+        let allowed = match kind {
+            Kind::Str => matches!(op, Op::Eq | Op::Ne | Op::Match | Op::NotMatch),
+            Kind::Num => !matches!(op, Op::Match | Op::NotMatch),
+            Kind::Bool => matches!(op, Op::Eq | Op::Ne),
+        };
+        if !allowed {
+            return Err(format!("`{}` does not support `{}`", field, op.symbol()));
+        }
+        self.next();
+        let value = match (kind, self.next()) {
+            (Kind::Str, Some(Token::Str(s))) => Value::Str(s),
+            (Kind::Num, Some(Token::Num(n))) => Value::Num(n),
+            (Kind::Bool, Some(Token::Ident(b))) if b == "true" || b == "false" => {
+                Value::Bool(b == "true")
+            }
+            (Kind::Str, _) => return Err(format!("`{}` is compared to a \"string\"", field)),
+            (Kind::Num, _) => return Err(format!("`{}` is compared to a number", field)),
+            (Kind::Bool, _) => return Err(format!("`{}` is compared to true or false", field)),
+        };
+        if let (Op::Eq | Op::Ne, Value::Str(s)) = (&op, &value) {
+            if field == "status" && RunStatus::from_name(s).is_none() {
+                return Err(format!(
+                    "Unknown status `{}` in filter, known: SUCCESS, DOUBTED, TIMEOUT, MEMOUT, CRASHED",
+                    s
+                ));
+            }
+        }
+        let regex = match (&op, &value) {
+            (Op::Match | Op::NotMatch, Value::Str(s)) => {
+                Some(Regex::new(s).map_err(|e| format!("Bad regex in filter: {}", e))?)
+            }
+            _ => None,
+        };
+        Ok(Expr::Compare {
+            field,
+            op,
+            value,
+            regex,
+        })
+    }
+}
+
+/// What a filter is evaluated on: one config of one experiment, or one of
+/// its runs. The config metadata is missing for runs coming from the db.
+pub struct Subject<'a> {
+    pub exp_id: &'a str,
+    pub config_id: &'a str,
+    pub model: &'a str,
+    pub instance: &'a str,
+    pub freq: f64,
+    pub config: Option<&'a ConfigMultiple>,
+    pub run: Option<&'a SolveInformation>,
+}
+
+impl Subject<'_> {
+    fn value(&self, field: &str) -> Option<Value> {
+        let (config, run) = (self.config, self.run);
+        let text = |s: &str| Some(Value::Str(s.to_string()));
+        let flag = |f: fn(&ConfigMultiple) -> bool| config.map(|c| Value::Bool(f(c)));
+        match field {
+            "status" => run.map(|s| Value::Str(Run::from_solve("", s).status.as_str().into())),
+            "machine_info" => run.and_then(|s| text(s.machine_info())),
+            "seed" => run.and_then(|s| s.seed()).map(Value::Num),
+            "memory_limit" => run.map(|s| Value::Num(s.memory_limit() as f64)),
+            "time_limit" => run
+                .and_then(|s| s.time_limit())
+                .map(|t| Value::Num(t as f64)),
+            "exp_id" => text(self.exp_id),
+            "config_id" => text(self.config_id),
+            "model" => text(self.model),
+            "instance" => text(self.instance),
+            "freq" => Some(Value::Num(self.freq)),
+            "preprocess" => config.and_then(|c| text(&c.preprocess)),
+            "representation" => config.and_then(|c| text(&c.representation)),
+            "solver" => config.and_then(|c| text(&c.solver)),
+            "incomparability" => flag(|c| c.incomparability),
+            "interactive" => flag(|c| c.interactive),
+            "native" => flag(|c| c.native),
+            "compressed" => flag(|c| c.compressed),
+            "ordered" => flag(|c| c.ordered),
+            "no_solution_blocking" => flag(|c| c.no_solution_blocking),
+            "mdd" => flag(|c| c.mdd),
+            "cgroups" => flag(|c| c.cgroups),
+            _ => None,
+        }
+    }
+}
+
+/// A parsed filter expression.
+#[derive(Debug)]
+pub struct Filter {
+    expr: Expr,
+}
+
+impl Filter {
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let mut parser = Parser {
+            tokens: tokenize(source)?,
+            pos: 0,
+        };
+        let expr = parser.or()?;
+        match parser.peek() {
+            None => Ok(Filter { expr }),
+            Some(t) => Err(format!("Unexpected {:?} in filter", t)),
+        }
+    }
+
+    /// True only if the filter is known to hold for the subject.
+    pub fn matches(&self, subject: &Subject) -> bool {
+        eval(&self.expr, subject) == Some(true)
+    }
+
+    /// True if the filter uses config metadata or run fields, which queries
+    /// on the db do not see.
+    pub fn uses_metadata(&self) -> bool {
+        self.uses(Scope::Config) || self.uses(Scope::Run)
+    }
+
+    /// True if the filter uses config metadata, which db rows do not have.
+    pub fn uses_config_metadata(&self) -> bool {
+        self.uses(Scope::Config)
+    }
+
+    fn uses(&self, scope: Scope) -> bool {
+        fn uses(expr: &Expr, scope: Scope) -> bool {
+            match expr {
+                Expr::And(a, b) | Expr::Or(a, b) => uses(a, scope) || uses(b, scope),
+                Expr::Not(e) => uses(e, scope),
+                Expr::Flag(field) | Expr::Compare { field, .. } => {
+                    field_kind(field).is_some_and(|(_, s)| s == scope)
+                }
+            }
+        }
+        uses(&self.expr, scope)
+    }
+
+    /// True if the filter selects one config of a store experiment. With run
+    /// fields, at least one of its runs has to be selected.
+    pub fn matches_config(
+        &self,
+        exp_id: &str,
+        e: &ExperimentMultiple,
+        config_id: &str,
+        config: &ConfigMultiple,
+    ) -> bool {
+        let subject = |run| Subject {
+            exp_id,
+            config_id,
+            model: &e.model,
+            instance: &e.instance,
+            freq: e.freq,
+            config: Some(config),
+            run,
+        };
+        if self.uses(Scope::Run) {
+            config
+                .solve_information
+                .iter()
+                .any(|s| self.matches(&subject(Some(s))))
+        } else {
+            self.matches(&subject(None))
+        }
+    }
+
+    /// True if the filter selects the run of a side file.
+    pub fn matches_single(&self, e: &ExperimentSingle) -> bool {
+        self.matches(&Subject {
+            exp_id: &e.exp_id,
+            config_id: &e.config_id,
+            model: &e.model,
+            instance: &e.instance,
+            freq: e.freq,
+            config: Some(&e.config),
+            run: e.config.solve_information.first(),
+        })
+    }
+
+    /// True if the filter selects a config known only by its ids, e.g. runs
+    /// of the db. Model, instance and freq come from the experiment id.
+    pub fn matches_ids(&self, exp_id: &str, config_id: &str) -> bool {
+        let (model, instance, freq) = split_exp_id(exp_id);
+        self.matches(&Subject {
+            exp_id,
+            config_id,
+            model: &model,
+            instance: &instance,
+            freq,
+            config: None,
+            run: None,
+        })
+    }
+
+    /// True if the filter selects a db row. Model, instance and freq come
+    /// from the experiment id, and the row has no config metadata.
+    pub fn matches_row(&self, row: &DBRow) -> bool {
+        let (exp_id, config_id) = (row.exp_id.as_str(), row.config_id.as_str());
+        let run = row.to_solve();
+        let (model, instance, freq) = split_exp_id(exp_id);
+        self.matches(&Subject {
+            exp_id,
+            config_id,
+            model: &model,
+            instance: &instance,
+            freq,
+            config: None,
+            run: run.as_ref(),
+        })
+    }
+}
+
+/// Evaluates with three-valued logic: a comparison on a field the subject
+/// does not have is unknown (`None`), and stays unknown under `!`.
+fn eval(expr: &Expr, subject: &Subject) -> Option<bool> {
+    match expr {
+        Expr::And(a, b) => match (eval(a, subject), eval(b, subject)) {
+            (Some(false), _) | (_, Some(false)) => Some(false),
+            (Some(true), Some(true)) => Some(true),
+            _ => None,
+        },
+        Expr::Or(a, b) => match (eval(a, subject), eval(b, subject)) {
+            (Some(true), _) | (_, Some(true)) => Some(true),
+            (Some(false), Some(false)) => Some(false),
+            _ => None,
+        },
+        Expr::Not(e) => eval(e, subject).map(|b| !b),
+        Expr::Flag(field) => match subject.value(field)? {
+            Value::Bool(b) => Some(b),
+            _ => None,
+        },
+        Expr::Compare {
+            field,
+            op,
+            value,
+            regex,
+        } => match (subject.value(field)?, value) {
+            (Value::Str(s), Value::Str(v)) => match op {
+                Op::Eq => Some(s == *v),
+                Op::Ne => Some(s != *v),
+                Op::Match => Some(regex.as_ref().unwrap().is_match(&s)),
+                Op::NotMatch => Some(!regex.as_ref().unwrap().is_match(&s)),
+                _ => None,
+            },
+            (Value::Num(n), Value::Num(v)) => match op {
+                Op::Eq => Some(n == *v),
+                Op::Ne => Some(n != *v),
+                Op::Lt => Some(n < *v),
+                Op::Le => Some(n <= *v),
+                Op::Gt => Some(n > *v),
+                Op::Ge => Some(n >= *v),
+                _ => None,
+            },
+            (Value::Bool(b), Value::Bool(v)) => match op {
+                Op::Eq => Some(b == *v),
+                Op::Ne => Some(b != *v),
+                _ => None,
+            },
+            _ => None,
+        },
+    }
+}
+
+impl ExperimentStore {
+    /// Keeps only the configs selected by the filter, or only the selected
+    /// runs if it uses run fields, and the experiments left with some.
+    pub fn retain_matching(&mut self, filter: &Filter) {
+        self.select(filter, true);
+    }
+
+    /// Removes the configs selected by the filter, or only the selected runs
+    /// if it uses run fields, and the experiments left empty. Returns the
+    /// number of removed experiments, configs and runs.
+    pub fn remove_matching(&mut self, filter: &Filter) -> (usize, usize, usize) {
+        self.select(filter, false)
+    }
+
+    /// Keeps what the filter selects if `keep`, else what it does not. Only
+    /// configs and experiments emptied by the filter are dropped when
+    /// removing.
+    fn select(&mut self, filter: &Filter, keep: bool) -> (usize, usize, usize) {
+        let by_run = filter.uses(Scope::Run);
+        let (mut experiments, mut configs, mut runs) = (0, 0, 0);
+        self.experiments.retain(|exp_id, e| {
+            let (model, instance, freq) = (&e.model, &e.instance, e.freq);
+            let had_configs = !e.configs.is_empty();
+            e.configs.retain(|config_id, c| {
+                let subject = |config, run| Subject {
+                    exp_id,
+                    config_id,
+                    model,
+                    instance,
+                    freq,
+                    config: Some(config),
+                    run,
+                };
+                let before = c.solve_information.len();
+                let dropped = if by_run {
+                    let kept: Vec<bool> = c
+                        .solve_information
+                        .iter()
+                        .map(|s| filter.matches(&subject(c, Some(s))) == keep)
+                        .collect();
+                    let mut kept = kept.into_iter();
+                    c.solve_information.retain(|_| kept.next().unwrap());
+                    c.solve_information.is_empty() && (keep || before > 0)
+                } else {
+                    filter.matches(&subject(c, None)) != keep
+                };
+                runs += match dropped {
+                    true => before,
+                    false => before - c.solve_information.len(),
+                };
+                configs += dropped as usize;
+                !dropped
+            });
+            let emptied = e.configs.is_empty() && (keep || had_configs);
+            experiments += emptied as usize;
+            !emptied
+        });
+        (experiments, configs, runs)
+    }
+}
+
+impl ExperimentStore {
+    /// Same as `retain_matching` on ids only, for stores exported from the db
+    /// whose metadata is left empty.
+    pub fn retain_matching_ids(&mut self, filter: &Filter) {
+        self.experiments.retain(|exp_id, e| {
+            e.configs
+                .retain(|config_id, _| filter.matches_ids(exp_id, config_id));
+            !e.configs.is_empty()
+        });
+    }
+}
+
+/// Backend answering from the runs of another one selected by a filter on
+/// ids, e.g. a db which has no config metadata.
+pub struct FilteredBackend<'a> {
+    pub inner: &'a mut dyn ResultBackend,
+    pub filter: &'a Filter,
+}
+
+impl ResultBackend for FilteredBackend<'_> {
+    fn experiment_runs(&mut self, exp_id: &str) -> Vec<Run> {
+        let mut runs = self.inner.experiment_runs(exp_id);
+        runs.retain(|r| self.filter.matches_ids(exp_id, &r.config_id));
+        runs
+    }
+
+    fn config_runs(&mut self, exp_id: &str, config_id: &str) -> Vec<Run> {
+        match self.filter.matches_ids(exp_id, config_id) {
+            true => self.inner.config_runs(exp_id, config_id),
+            false => vec![],
+        }
+    }
+
+    fn batch_runs(&mut self, exp_ids: &[String]) -> RunCache {
+        let mut cache = self.inner.batch_runs(exp_ids);
+        cache.retain(|exp_id, r| self.filter.matches_ids(exp_id, &r.config_id));
+        cache
+    }
+
+    fn answer_all(&mut self, queries: &[Query]) -> Vec<QueryRecord> {
+        self.batch_runs(&batch_exp_ids(queries)).answer_all(queries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn timeout(seed: Option<f64>, machine_info: &str) -> SolveInformation {
+        SolveInformation::Timeout {
+            seed,
+            memory_limit: 4096,
+            time_limit: 3600,
+            machine_info: machine_info.to_string(),
+        }
+    }
+
+    fn config(solver: &str, cgroups: bool, runs: Vec<SolveInformation>) -> ConfigMultiple {
+        ConfigMultiple {
+            solver: solver.to_string(),
+            cgroups,
+            solve_information: runs,
+            ..Default::default()
+        }
+    }
+
+    fn experiment(configs: Vec<(&str, ConfigMultiple)>) -> ExperimentMultiple {
+        let configs: HashMap<String, Box<ConfigMultiple>> = configs
+            .into_iter()
+            .map(|(id, c)| (id.to_string(), Box::new(c)))
+            .collect();
+        ExperimentMultiple::new("mA".to_string(), "i1".to_string(), 0.1, configs)
+    }
+
+    fn parse(source: &str) -> Filter {
+        Filter::parse(source).unwrap()
+    }
+
+    fn parse_error(source: &str) -> String {
+        Filter::parse(source).unwrap_err()
+    }
+
+    #[test]
+    fn tokenizes_operators_strings_and_numbers() {
+        let tokens = tokenize(r#"model=="a \"b\""&&(freq>=-0.5||!cgroups) seed<1e3"#).unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Ident("model".to_string()),
+                Token::Op(Op::Eq),
+                Token::Str("a \"b\"".to_string()),
+                Token::And,
+                Token::Open,
+                Token::Ident("freq".to_string()),
+                Token::Op(Op::Ge),
+                Token::Num(-0.5),
+                Token::Or,
+                Token::Not,
+                Token::Ident("cgroups".to_string()),
+                Token::Close,
+                Token::Ident("seed".to_string()),
+                Token::Op(Op::Lt),
+                Token::Num(1000.0),
+            ]
+        );
+        assert_eq!(
+            tokenize("a != b =~ c !~ d <= e > f").unwrap()[1..]
+                .iter()
+                .step_by(2)
+                .cloned()
+                .collect::<Vec<_>>(),
+            vec![
+                Token::Op(Op::Ne),
+                Token::Op(Op::Match),
+                Token::Op(Op::NotMatch),
+                Token::Op(Op::Le),
+                Token::Op(Op::Gt),
+            ]
+        );
+    }
+
+    #[test]
+    fn tokenizer_errors() {
+        assert_eq!(
+            tokenize(r#"model == "x"#),
+            Err("Unterminated string in filter".to_string())
+        );
+        assert_eq!(
+            tokenize("freq == 1.2.3"),
+            Err("Bad number `1.2.3` in filter".to_string())
+        );
+        assert_eq!(
+            tokenize("model = \"x\""),
+            Err("Unexpected `=` in filter".to_string())
+        );
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or_and_not_tighter_than_both() {
+        // parsed as `model == "mA" || (model == "x" && solver == "none")`
+        assert!(
+            parse(r#"model == "mA" || model == "x" && solver == "none""#)
+                .matches_ids("mA_i1_0.1", "c")
+        );
+        assert!(
+            !parse(r#"(model == "mA" || model == "x") && config_id == "none""#)
+                .matches_ids("mA_i1_0.1", "c")
+        );
+        // parsed as `(!(model == "x")) || model == "mA"`, not `!(... || ...)`
+        assert!(parse(r#"!model == "x" || model == "mA""#).matches_ids("mA_i1_0.1", "c"));
+        assert!(parse(r#"!!(model == "mA")"#).matches_ids("mA_i1_0.1", "c"));
+    }
+
+    #[test]
+    fn id_fields_come_from_the_experiment_id() {
+        let filter = parse(r#"model == "mA" && instance =~ "^i[0-9]$" && freq < 0.2"#);
+        assert!(filter.matches_ids("mA_i1_0.1", "c"));
+        assert!(!filter.matches_ids("mA_i1_0.3", "c"));
+        assert!(!filter.matches_ids("mB_i1_0.1", "c"));
+        assert!(parse(r#"exp_id == "mA_i1_0.1" && config_id != "d""#).matches_ids("mA_i1_0.1", "c"));
+        assert!(!parse(r#"config_id !~ "^c""#).matches_ids("mA_i1_0.1", "c"));
+    }
+
+    #[test]
+    fn config_fields_come_from_the_metadata() {
+        let e = experiment(vec![]);
+        let kissat = config("kissat", true, vec![]);
+        let cplex = config("cplex", false, vec![]);
+        let filter = parse(r#"solver =~ "kiss" && cgroups"#);
+        assert!(filter.matches_config("mA_i1_0.1", &e, "c", &kissat));
+        assert!(!filter.matches_config("mA_i1_0.1", &e, "c", &cplex));
+        assert!(parse("cgroups == false && !mdd").matches_config("mA_i1_0.1", &e, "c", &cplex));
+    }
+
+    #[test]
+    fn run_fields_select_a_config_with_any_matching_run() {
+        let e = experiment(vec![]);
+        let c = config(
+            "kissat",
+            true,
+            vec![timeout(Some(1.0), "m1"), timeout(None, "m2")],
+        );
+        assert!(parse(r#"machine_info == "m2""#).matches_config("mA_i1_0.1", &e, "c", &c));
+        assert!(parse(r#"status == "TIMEOUT" && seed >= 1"#).matches_config(
+            "mA_i1_0.1",
+            &e,
+            "c",
+            &c
+        ));
+        assert!(!parse(r#"status == "SUCCESS""#).matches_config("mA_i1_0.1", &e, "c", &c));
+        assert!(!parse("time_limit > 3600").matches_config("mA_i1_0.1", &e, "c", &c));
+    }
+
+    #[test]
+    fn missing_fields_stay_unknown_under_negation() {
+        // db configs have no metadata
+        assert!(!parse("cgroups").matches_ids("mA_i1_0.1", "c"));
+        assert!(!parse("!cgroups").matches_ids("mA_i1_0.1", "c"));
+        assert!(!parse(r#"!(solver == "kissat")"#).matches_ids("mA_i1_0.1", "c"));
+        assert!(!parse(r#"cgroups && model == "mA""#).matches_ids("mA_i1_0.1", "c"));
+        assert!(parse(r#"cgroups || model == "mA""#).matches_ids("mA_i1_0.1", "c"));
+        // a known false side decides `&&` whatever the other side is
+        assert!(parse(r#"!(cgroups && model == "mB")"#).matches_ids("mA_i1_0.1", "c"));
+        // a run without a seed
+        let e = experiment(vec![]);
+        let c = config("kissat", true, vec![timeout(None, "m1")]);
+        assert!(!parse("seed == 1").matches_config("mA_i1_0.1", &e, "c", &c));
+        assert!(!parse("!(seed == 1)").matches_config("mA_i1_0.1", &e, "c", &c));
+    }
+
+    #[test]
+    fn db_rows_have_run_fields_but_no_metadata() {
+        let row = DBRow {
+            exp_id: "mA_i1_0.1".to_string(),
+            config_id: "c".to_string(),
+            result_type: "TIMEOUT".to_string(),
+            measured_time: 3600.0,
+            nb_solutions: None,
+            machine_info: "m1".to_string(),
+            memory_limit: 4096,
+            seed: Some(1.0),
+            solver_time: None,
+            total_nodes: None,
+            time_limit: Some(3600),
+            freq_nb_solutions: None,
+            level_info: None,
+        };
+        assert!(
+            parse(r#"status == "TIMEOUT" && machine_info == "m1" && freq == 0.1"#)
+                .matches_row(&row)
+        );
+        assert!(!parse(r#"solver == "kissat""#).matches_row(&row));
+        assert!(!parse(r#"solver != "kissat""#).matches_row(&row));
+    }
+
+    #[test]
+    fn reports_which_scopes_a_filter_uses() {
+        let ids = parse(r#"model == "mA" || !(freq > 1)"#);
+        assert!(!ids.uses_metadata() && !ids.uses_config_metadata());
+        let run = parse(r#"model == "mA" && status == "CRASHED""#);
+        assert!(run.uses_metadata() && !run.uses_config_metadata());
+        let config = parse(r#"model == "mA" || !cgroups"#);
+        assert!(config.uses_metadata() && config.uses_config_metadata());
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(parse_error("modl == \"x\"")
+            .starts_with("Unknown field `modl` in filter, known fields: exp_id, config_id"));
+        assert!(parse_error(r#"solver =~ "(kissat""#).starts_with("Bad regex in filter: "));
+        assert_eq!(
+            parse_error("model =="),
+            "`model` is compared to a \"string\""
+        );
+        assert_eq!(parse_error(r#"model == "x" &&"#), "Filter ends too early");
+        assert_eq!(parse_error(""), "Filter ends too early");
+        assert_eq!(parse_error(r#"(model == "x""#), "Missing `)` in filter");
+        assert_eq!(
+            parse_error(r#"model == "x")"#),
+            "Unexpected Close in filter"
+        );
+        assert_eq!(
+            parse_error("model"),
+            "`model` needs to be compared to a value"
+        );
+        assert_eq!(parse_error("freq =~ \"1\""), "`freq` does not support `=~`");
+        assert_eq!(
+            parse_error("freq == \"1\""),
+            "`freq` is compared to a number"
+        );
+        assert_eq!(
+            parse_error("cgroups == 1"),
+            "`cgroups` is compared to true or false"
+        );
+        assert!(parse_error(r#"status == "DONE""#).starts_with("Unknown status `DONE` in filter"));
+    }
+
+    #[test]
+    fn removes_runs_configs_and_emptied_experiments() {
+        let mut store = ExperimentStore {
+            experiments: HashMap::new(),
+        };
+        let e = experiment(vec![
+            (
+                "c1",
+                config(
+                    "kissat",
+                    true,
+                    vec![timeout(Some(1.0), "m1"), timeout(Some(2.0), "m2")],
+                ),
+            ),
+            ("c2", config("cplex", true, vec![timeout(Some(1.0), "m2")])),
+        ]);
+        store
+            .experiments
+            .insert("mA_i1_0.1".to_string(), Box::new(e));
+        let mut kept = ExperimentStore {
+            experiments: HashMap::new(),
+        };
+        kept.experiments.insert(
+            "mA_i1_0.1".to_string(),
+            Box::new(experiment(vec![(
+                "c1",
+                config("kissat", true, vec![timeout(None, "m1")]),
+            )])),
+        );
+        kept.retain_matching(&parse(r#"solver == "kissat""#));
+        assert_eq!(kept.experiments.len(), 1);
+
+        assert_eq!(
+            store.remove_matching(&parse(r#"machine_info == "m2""#)),
+            (0, 1, 2)
+        );
+        let e = &store.experiments["mA_i1_0.1"];
+        assert_eq!(e.configs.len(), 1);
+        assert_eq!(e.configs["c1"].solve_information.len(), 1);
+        assert_eq!(
+            store.remove_matching(&parse(r#"solver == "kissat""#)),
+            (1, 1, 1)
+        );
+        assert!(store.experiments.is_empty());
+    }
+}
@@ -0,0 +1,248 @@
+//! Indexed binary store (`.rrrs`). Every config of every experiment is a
+//! separately compressed json block, so a query only decompresses the blocks
+//! it needs instead of parsing the whole store.
+//!
+//! Layout: the `RRRS` magic and a little endian `u32` format version, the
+//! blocks, the compressed json index, and the `u64` offset of the index as
+//! the last 8 bytes.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom, Write};
+
+use crate::backend::{ResultBackend, Run};
+use crate::json::{ConfigMultiple, ExperimentMultiple, ExperimentStore};
+use crate::utils;
+
+const MAGIC: &[u8; 4] = b"RRRS";
+const VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, Clone, Copy)]
+struct Block {
+    offset: u64,
+    len: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ExperimentEntry {
+    model: String,
+    instance: String,
+    freq: f64,
+    configs: BTreeMap<String, Block>,
+}
+
+/// Writes a store in the indexed format, zstd compressing every block at
+/// `level`. Experiments and configs are written in id order so the same store
+/// always gives the same file. Blocks are streamed to the file as they are
+/// compressed, only the index is kept until the end.
+pub fn write_indexed(store: &ExperimentStore, path: &str, level: i32) {
+    utils::write_streamed(path, |w| {
+        let mut offset = 0;
+        write_counted(w, &mut offset, MAGIC);
+        write_counted(w, &mut offset, &VERSION.to_le_bytes());
+        let mut index = BTreeMap::new();
+        let exps: BTreeMap<_, _> = store.experiments.iter().collect();
+        for (exp_id, e) in exps {
+            let mut configs = BTreeMap::new();
+            let sorted: BTreeMap<_, _> = e.configs.iter().collect();
+            for (c_id, config) in sorted {
+                let block = utils::compress_bytes(&serde_json::to_vec(config).unwrap(), level);
+                configs.insert(
+                    c_id.clone(),
+                    Block {
+                        offset,
+                        len: block.len() as u64,
+                    },
+                );
+                write_counted(w, &mut offset, &block);
+            }
+            index.insert(
+                exp_id.clone(),
+                ExperimentEntry {
+                    model: e.model.clone(),
+                    instance: e.instance.clone(),
+                    freq: e.freq,
+                    configs,
+                },
+            );
+        }
+        let index_offset = offset;
+        let index = utils::compress_bytes(&serde_json::to_vec(&index).unwrap(), level);
+        write_counted(w, &mut offset, &index);
+        write_counted(w, &mut offset, &index_offset.to_le_bytes());
+    });
+}
+
+/// Writes `bytes` and moves `offset` past them.
+fn write_counted(w: &mut dyn Write, offset: &mut u64, bytes: &[u8]) {
+    w.write_all(bytes).expect("Unable to write to file");
+    *offset += bytes.len() as u64;
+}
+
+/// Random access reader over an indexed store. Only the index is read when
+/// opening, blocks are read on demand.
+pub struct IndexedStore {
+    file: File,
+    index: HashMap<String, ExperimentEntry>,
+}
+
+impl IndexedStore {
+    pub fn open(path: &str) -> Self {
+        let mut file = File::open(path).expect("Unable to read from file");
+        let mut header = [0u8; 8];
+        file.read_exact(&mut header)
+            .expect("Unable to read the indexed store header");
+        assert!(&header[..4] == MAGIC, "{} is not an indexed store", path);
+        let version = u32::from_le_bytes(header[4..].try_into().unwrap());
+        assert!(
+            version == VERSION,
+            "Indexed store format version {} is not supported by this rrr ({}).",
+            version,
+            VERSION
+        );
+        let end = file.seek(SeekFrom::End(0)).unwrap();
+        assert!(end >= 16, "{} is truncated", path);
+        let mut footer = [0u8; 8];
+        let end = file.seek(SeekFrom::End(-8)).unwrap();
+        file.read_exact(&mut footer).unwrap();
+        let index_offset = u64::from_le_bytes(footer);
+        assert!(
+            (8..end).contains(&index_offset),
+            "{} is truncated: its index offset is past the end of the file",
+            path
+        );
+        let index = read_block(
+            &mut file,
+            Block {
+                offset: index_offset,
+                len: end - index_offset,
+            },
+        );
+        IndexedStore { file, index }
+    }
+
+    /// Ids of every experiment in the store.
+    pub fn exp_ids(&self) -> impl Iterator<Item = &String> {
+        self.index.keys()
+    }
+
+    fn read_config(&mut self, exp_id: &str, config_id: &str) -> Option<ConfigMultiple> {
+        let block = *self.index.get(exp_id)?.configs.get(config_id)?;
+        Some(read_block(&mut self.file, block))
+    }
+
+    /// Reads every config of one experiment.
+    pub fn load(&mut self, exp_id: &str) -> Option<ExperimentMultiple> {
+        let entry = self.index.get(exp_id)?;
+        let blocks: Vec<(String, Block)> =
+            entry.configs.iter().map(|(c, b)| (c.clone(), *b)).collect();
+        let mut e = ExperimentMultiple::new(
+            entry.model.clone(),
+            entry.instance.clone(),
+            entry.freq,
+            HashMap::new(),
+        );
+        for (c_id, block) in blocks {
+            e.configs
+                .insert(c_id, Box::new(read_block(&mut self.file, block)));
+        }
+        Some(e)
+    }
+
+    /// Reads the whole store.
+    pub fn to_store(mut self) -> ExperimentStore {
+        let exp_ids: Vec<String> = self.exp_ids().cloned().collect();
+        self.load_store(&exp_ids)
+    }
+
+    /// Reads the given experiments into a store, skipping unknown ids.
+    pub fn load_store(&mut self, exp_ids: &[String]) -> ExperimentStore {
+        let mut store = ExperimentStore {
+            experiments: HashMap::new(),
+        };
+        for exp_id in exp_ids {
+            if let Some(e) = self.load(exp_id) {
+                store.experiments.insert(exp_id.clone(), Box::new(e));
+            }
+        }
+        store
+    }
+}
+
+fn read_block<T: for<'de> Deserialize<'de>>(file: &mut File, block: Block) -> T {
+    let mut data = vec![0u8; block.len as usize];
+    file.seek(SeekFrom::Start(block.offset)).unwrap();
+    file.read_exact(&mut data).expect("Truncated indexed store");
+    let json = utils::decompress_bytes(&data).expect("Truncated or damaged indexed store");
+    serde_json::from_slice(&json).expect("Truncated or damaged indexed store")
+}
+
+impl ResultBackend for IndexedStore {
+    fn experiment_runs(&mut self, exp_id: &str) -> Vec<Run> {
+        self.load(exp_id).map_or_else(Vec::new, |e| e.runs())
+    }
+
+    fn config_runs(&mut self, exp_id: &str, config_id: &str) -> Vec<Run> {
+        self.read_config(exp_id, config_id)
+            .map_or_else(Vec::new, |c| {
+                c.solve_information
+                    .iter()
+                    .map(|s| Run::from_solve(config_id, s))
+                    .collect()
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::table_tests::sample_store;
+    use std::fs;
+
+    fn written(name: &str, level: i32) -> (ExperimentStore, String) {
+        let store = sample_store();
+        let path = format!("{}/store.rrrs", utils::test_dir(name));
+        write_indexed(&store, &path, level);
+        (store, path)
+    }
+
+    #[test]
+    fn write_then_load_gives_the_same_store() {
+        for level in [1, 19] {
+            let (store, path) = written(&format!("indexed-{}", level), level);
+            let loaded = IndexedStore::open(&path).to_store();
+            assert_eq!(
+                serde_json::to_value(&loaded).unwrap(),
+                serde_json::to_value(&store).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn experiments_and_configs_load_one_at_a_time() {
+        let (mut store, path) = written("indexed-random", 1);
+        let mut indexed = IndexedStore::open(&path);
+        let e = indexed.load("mB_i2_0.5").unwrap();
+        assert_eq!(
+            serde_json::to_value(&e).unwrap(),
+            serde_json::to_value(&store.experiments["mB_i2_0.5"]).unwrap()
+        );
+        assert!(indexed.load("mZ_i9_1").is_none());
+        let runs = indexed.config_runs("mA_i1_0.1", "c_kissat");
+        assert_eq!(runs.len(), 2);
+        assert_eq!(runs, store.config_runs("mA_i1_0.1", "c_kissat"));
+        let some = indexed.load_store(&["mB_i2_0.5".to_string(), "mZ_i9_1".to_string()]);
+        assert_eq!(some.experiments.len(), 1);
+        assert!(some.experiments.contains_key("mB_i2_0.5"));
+    }
+
+    #[test]
+    #[should_panic(expected = "is truncated")]
+    fn a_truncated_file_is_rejected() {
+        let (_, path) = written("indexed-truncated", 1);
+        let file = fs::OpenOptions::new().write(true).open(&path).unwrap();
+        file.set_len(file.metadata().unwrap().len() - 3).unwrap();
+        IndexedStore::open(&path);
+    }
+}
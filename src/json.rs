@@ -1,10 +1,17 @@
+use rayon::prelude::*;
+use serde::de::{self, DeserializeSeed, Deserializer, IgnoredAny, MapAccess, Visitor};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::fs;
+use std::io::Read;
+use std::path::Path;
 
-use crate::utils::{self, read_file, FSort, Mode};
+use crate::backend::{best_of, Run};
+use crate::filter::Filter;
+use crate::utils::{self, read_file, Compression};
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, Default)]
 pub struct LevelInformation {
     pub solver_time: Option<HashMap<String, f64>>,
     pub nodes: Option<HashMap<String, u64>>,
@@ -76,36 +83,183 @@ pub enum SolveInformation {
     },
 }
 
-#[derive(Serialize, Deserialize)]
+/// Type, seed, machine, memory and time limits, sr time and solver time of
+/// a run. DOUBTED runs have the type of SUCCESS ones, floats are compared bit
+/// for bit.
+type RunIdentity<'a> = (
+    &'static str,
+    Option<u64>,
+    &'a str,
+    u64,
+    Option<u64>,
+    Option<u64>,
+    Option<u64>,
+);
+
+impl SolveInformation {
+    /// Type, seed, machine, limits and measured times of a run.
+    fn identity(&self) -> RunIdentity<'_> {
+        match self {
+            SolveInformation::Success {
+                seed,
+                memory_limit,
+                time_limit,
+                machine_info,
+                total_sr_time,
+                total_solver_time,
+                ..
+            }
+            | SolveInformation::Doubted {
+                seed,
+                memory_limit,
+                time_limit,
+                machine_info,
+                total_sr_time,
+                total_solver_time,
+                ..
+            } => (
+                "SUCCESS",
+                seed.map(f64::to_bits),
+                machine_info,
+                *memory_limit,
+                Some(*time_limit),
+                Some(total_sr_time.to_bits()),
+                Some(total_solver_time.to_bits()),
+            ),
+            SolveInformation::Timeout {
+                seed,
+                memory_limit,
+                time_limit,
+                machine_info,
+            } => (
+                "TIMEOUT",
+                seed.map(f64::to_bits),
+                machine_info,
+                *memory_limit,
+                Some(*time_limit),
+                None,
+                None,
+            ),
+            SolveInformation::Memout {
+                seed,
+                memory_limit,
+                time_limit,
+                machine_info,
+                crash_time,
+            } => (
+                "MEMOUT",
+                seed.map(f64::to_bits),
+                machine_info,
+                *memory_limit,
+                Some(*time_limit),
+                Some(crash_time.to_bits()),
+                None,
+            ),
+            SolveInformation::Crash {
+                seed,
+                memory_limit,
+                time_limit,
+                machine_info,
+                crash_time,
+            } => (
+                "CRASHED",
+                seed.map(f64::to_bits),
+                machine_info,
+                *memory_limit,
+                *time_limit,
+                Some(crash_time.to_bits()),
+                None,
+            ),
+        }
+    }
+
+    pub fn seed(&self) -> Option<f64> {
+        self.identity().1.map(f64::from_bits)
+    }
+
+    pub fn machine_info(&self) -> &str {
+        self.identity().2
+    }
+
+    pub fn memory_limit(&self) -> u64 {
+        self.identity().3
+    }
+
+    /// Time limit of the run, which old crashed runs do not have.
+    pub fn time_limit(&self) -> Option<u64> {
+        self.identity().4
+    }
+
+    /// True if both are the same run, e.g. a side file merged twice: same
+    /// type, seed, machine, limits and times. A SUCCESS and a DOUBTED run can
+    /// be the same since merging may turn a SUCCESS into DOUBTED.
+    pub fn same_run(&self, other: &SolveInformation) -> bool {
+        self.identity() == other.identity()
+    }
+}
+
+#[derive(Serialize, Deserialize, Default)]
 pub struct ConfigMultiple {
-    preprocess: String,
-    representation: String,
-    solver: String,
-    incomparability: bool,
-    interactive: bool,
-    native: bool,
-    compressed: bool,
-    ordered: bool,
-    no_solution_blocking: bool,
-    mdd: bool,
-    cgroups: bool,
+    pub preprocess: String,
+    pub representation: String,
+    pub solver: String,
+    pub incomparability: bool,
+    pub interactive: bool,
+    pub native: bool,
+    pub compressed: bool,
+    pub ordered: bool,
+    pub no_solution_blocking: bool,
+    pub mdd: bool,
+    pub cgroups: bool,
     pub solve_information: Vec<SolveInformation>,
 }
 
+impl ConfigMultiple {
+    /// Names of the metadata fields that differ between two configs.
+    pub fn metadata_conflicts(&self, other: &ConfigMultiple) -> Vec<&'static str> {
+        [
+            ("preprocess", self.preprocess == other.preprocess),
+            (
+                "representation",
+                self.representation == other.representation,
+            ),
+            ("solver", self.solver == other.solver),
+            (
+                "incomparability",
+                self.incomparability == other.incomparability,
+            ),
+            ("interactive", self.interactive == other.interactive),
+            ("native", self.native == other.native),
+            ("compressed", self.compressed == other.compressed),
+            ("ordered", self.ordered == other.ordered),
+            (
+                "no_solution_blocking",
+                self.no_solution_blocking == other.no_solution_blocking,
+            ),
+            ("mdd", self.mdd == other.mdd),
+            ("cgroups", self.cgroups == other.cgroups),
+        ]
+        .into_iter()
+        .filter(|(_, same)| !same)
+        .map(|(name, _)| name)
+        .collect()
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct ExperimentSingle {
-    model: String,
-    instance: String,
-    freq: f64,
+    pub model: String,
+    pub instance: String,
+    pub freq: f64,
     pub exp_id: String,
     pub config_id: String,
     pub config: Box<ConfigMultiple>,
 }
 #[derive(Serialize, Deserialize)]
 pub struct ExperimentMultiple {
-    model: String,
-    instance: String,
-    freq: f64,
+    pub model: String,
+    pub instance: String,
+    pub freq: f64,
     pub configs: HashMap<String, Box<ConfigMultiple>>,
 }
 
@@ -130,18 +284,262 @@ pub struct ExperimentStore {
     pub experiments: HashMap<String, Box<ExperimentMultiple>>,
 }
 
+impl ExperimentStore {
+    /// Parses a whole store straight from a reader.
+    pub fn from_reader<R: Read>(reader: R) -> Self {
+        serde_json::from_reader(reader).unwrap()
+    }
+
+    /// Parses only the given experiments of a store. The others are skipped
+    /// by the parser without being built, which is much faster on big stores.
+    pub fn from_reader_only<R: Read>(reader: R, exp_ids: &[String]) -> Self {
+        let exp_ids: HashSet<&str> = exp_ids.iter().map(|e| e.as_str()).collect();
+        let mut de = serde_json::Deserializer::from_reader(reader);
+        let store = FilteredStore { exp_ids: &exp_ids }
+            .deserialize(&mut de)
+            .unwrap();
+        de.end().unwrap();
+        store
+    }
+}
+
+/// Store deserializer keeping only some experiments.
+struct FilteredStore<'a> {
+    exp_ids: &'a HashSet<&'a str>,
+}
+
+impl<'de> DeserializeSeed<'de> for FilteredStore<'_> {
+    type Value = ExperimentStore;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for FilteredStore<'_> {
+    type Value = ExperimentStore;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an experiment store")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut experiments = None;
+        while let Some(key) = map.next_key::<String>()? {
+            if key == "experiments" {
+                experiments = Some(map.next_value_seed(FilteredExperiments {
+                    exp_ids: self.exp_ids,
+                })?);
+            } else {
+                map.next_value::<IgnoredAny>()?;
+            }
+        }
+        Ok(ExperimentStore {
+            experiments: experiments.ok_or_else(|| de::Error::missing_field("experiments"))?,
+        })
+    }
+}
+
+struct FilteredExperiments<'a> {
+    exp_ids: &'a HashSet<&'a str>,
+}
+
+impl<'de> DeserializeSeed<'de> for FilteredExperiments<'_> {
+    type Value = HashMap<String, Box<ExperimentMultiple>>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for FilteredExperiments<'_> {
+    type Value = HashMap<String, Box<ExperimentMultiple>>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a map of experiments")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut experiments = HashMap::new();
+        while let Some(exp_id) = map.next_key::<String>()? {
+            if self.exp_ids.contains(exp_id.as_str()) {
+                experiments.insert(exp_id, map.next_value()?);
+            } else {
+                map.next_value::<IgnoredAny>()?;
+            }
+        }
+        Ok(experiments)
+    }
+}
+
+/// Merges side files into the store. The files are left in place, they may
+/// only be removed once the merged store is safely written.
 pub fn merge_mode(store: &mut ExperimentStore, input_files: Vec<&str>) {
+    merge_experiments(store, read_side_files(&input_files));
+}
+
+/// Parses side files, one single run experiment per line.
+pub fn read_side_files(input_files: &[&str]) -> Vec<ExperimentSingle> {
+    let mut experiments = vec![];
     for f in input_files {
-        let data = read_file(f, false);
+        let data = read_file(f);
         for line in data.lines() {
-            let experiment: ExperimentSingle = serde_json::from_str(line).unwrap();
-            merge_one_experiment(store, experiment);
+            experiments.push(serde_json::from_str(line).unwrap());
+        }
+    }
+    experiments
+}
+
+/// Merges single run experiments into the store, skipping runs it already has.
+pub fn merge_experiments(store: &mut ExperimentStore, experiments: Vec<ExperimentSingle>) {
+    let mut duplicates = 0;
+    for experiment in experiments {
+        if contains_run(store, &experiment) {
+            println!(
+                "Skipping a run already in the store: {} {}",
+                experiment.exp_id, experiment.config_id
+            );
+            duplicates += 1;
+            continue;
         }
-        fs::remove_file(f).expect("Unable to delete side file");
+        merge_one_experiment(store, experiment);
+    }
+    if duplicates > 0 {
+        println!("Skipped {} duplicate runs.", duplicates);
     }
 }
 
-fn merge_one_experiment(store: &mut ExperimentStore, experiment: ExperimentSingle) {
+/// Merges a whole store into `store`. New experiments and configs are moved
+/// in, runs of shared configs go through `merge_one_experiment` like side
+/// files, so a different nb of solutions turns them DOUBTED, and runs the
+/// store already has are skipped. Differing model, instance, freq or config
+/// metadata are reported and the values of `store` kept. Returns the number
+/// of merged runs, skipped runs and conflicts.
+pub fn merge_stores(store: &mut ExperimentStore, other: ExperimentStore) -> (usize, usize, usize) {
+    let (mut merged, mut skipped, mut conflicts) = (0, 0, 0);
+    let mut experiments: Vec<(String, Box<ExperimentMultiple>)> =
+        other.experiments.into_iter().collect();
+    experiments.sort_by(|a, b| a.0.cmp(&b.0));
+    for (exp_id, e) in experiments {
+        let Some(known) = store.experiments.get(&exp_id) else {
+            merged += e
+                .configs
+                .values()
+                .map(|c| c.solve_information.len())
+                .sum::<usize>();
+            store.experiments.insert(exp_id, e);
+            continue;
+        };
+        let differing = [
+            ("model", known.model != e.model),
+            ("instance", known.instance != e.instance),
+            ("freq", known.freq != e.freq),
+        ];
+        for (field, _) in differing.iter().filter(|(_, differ)| *differ) {
+            println!(
+                "Conflicting {} for {}, keeping the first one.",
+                field, exp_id
+            );
+            conflicts += 1;
+        }
+        let ExperimentMultiple {
+            model,
+            instance,
+            freq,
+            configs,
+        } = *e;
+        let mut configs: Vec<(String, Box<ConfigMultiple>)> = configs.into_iter().collect();
+        configs.sort_by(|a, b| a.0.cmp(&b.0));
+        for (config_id, config) in configs {
+            let Some(known) = store.experiments[&exp_id].configs.get(&config_id) else {
+                merged += config.solve_information.len();
+                let known = store.experiments.get_mut(&exp_id).unwrap();
+                known.configs.insert(config_id, config);
+                continue;
+            };
+            let fields = known.metadata_conflicts(&config);
+            if !fields.is_empty() {
+                println!(
+                    "Conflicting {} for {} {}, keeping the first one.",
+                    fields.join(", "),
+                    exp_id,
+                    config_id
+                );
+                conflicts += fields.len();
+            }
+            for solve in config.solve_information {
+                let experiment = ExperimentSingle {
+                    model: model.clone(),
+                    instance: instance.clone(),
+                    freq,
+                    exp_id: exp_id.clone(),
+                    config_id: config_id.clone(),
+                    config: Box::new(ConfigMultiple {
+                        solve_information: vec![solve],
+                        ..Default::default()
+                    }),
+                };
+                if contains_run(store, &experiment) {
+                    skipped += 1;
+                } else {
+                    merge_one_experiment(store, experiment);
+                    merged += 1;
+                }
+            }
+        }
+    }
+    (merged, skipped, conflicts)
+}
+
+/// True if the store already has the single run of `experiment`.
+pub fn contains_run(store: &ExperimentStore, experiment: &ExperimentSingle) -> bool {
+    let config = store
+        .experiments
+        .get(&experiment.exp_id)
+        .and_then(|e| e.configs.get(&experiment.config_id));
+    match (config, experiment.config.solve_information.first()) {
+        (Some(config), Some(new)) => config.solve_information.iter().any(|s| s.same_run(new)),
+        _ => false,
+    }
+}
+
+/// Removes repeated runs of every config selected by the filter, keeping the
+/// first one, or its SUCCESS copy if the first one is DOUBTED. Returns the
+/// number of removed runs.
+pub fn dedupe(store: &mut ExperimentStore, filter: Option<&Filter>) -> usize {
+    let mut removed = 0;
+    for (exp_id, exps) in &mut store.experiments {
+        let selected: Vec<String> = exps
+            .configs
+            .iter()
+            .filter(|(c_id, c)| filter.is_none_or(|f| f.matches_config(exp_id, exps, c_id, c)))
+            .map(|(c_id, _)| c_id.clone())
+            .collect();
+        for (c_id, configs) in &mut exps.configs {
+            if !selected.contains(c_id) {
+                continue;
+            }
+            let mut kept: Vec<SolveInformation> = vec![];
+            for solve in configs.solve_information.drain(..) {
+                match kept.iter_mut().find(|k| k.same_run(&solve)) {
+                    Some(k) => {
+                        println!("Removing a duplicate run: {} {}", exp_id, c_id);
+                        removed += 1;
+                        // the SUCCESS copy of a run is kept over a DOUBTED one
+                        if matches!(solve, SolveInformation::Success { .. }) {
+                            *k = solve;
+                        }
+                    }
+                    None => kept.push(solve),
+                }
+            }
+            configs.solve_information = kept;
+        }
+    }
+    removed
+}
+
+pub fn merge_one_experiment(store: &mut ExperimentStore, experiment: ExperimentSingle) {
     // if exp id is in
     if let Some(exp_multi) = store.experiments.get_mut(experiment.exp_id.as_str()) {
         // if config id is in
@@ -218,144 +616,47 @@ fn merge_one_experiment(store: &mut ExperimentStore, experiment: ExperimentSingl
     }
 }
 
-pub fn check_mode(
-    store: &ExperimentStore,
-    experiment_id: &str,
-    config_id: &str,
-    mode: Mode,
-    exact: bool,
-) {
-    // exp is in
-    if let Some(experiment) = store.experiments.get(experiment_id) {
-        if exact {
-            // config is in
-            if let Some(config) = experiment.configs.get(config_id) {
-                match get_best_time(config) {
-                    BestExperimentResult::Success {
-                        min_total_sr_time,
-                        min_total_solver_time,
-                        nb_solutions,
-                    } => match mode {
-                        Mode::SRTime => println!("MIN_SR {}", min_total_sr_time),
-                        Mode::SolverTime => println!("MIN_SOLVER {}", min_total_solver_time),
-                        Mode::NbSolutions => println!("NB_SOLS {}", nb_solutions),
-                    },
-                    BestExperimentResult::Timeout { max_time_limit } => {
-                        println!("MAX_TIMEOUT {}", max_time_limit);
-                    }
-                    BestExperimentResult::None => {
-                        println!("EMPTY");
-                    }
-                }
-            } else {
-                println!("EMPTY");
-            }
-        } else {
-            match get_virtual_best(&experiment.configs) {
-                BestExperimentResult::Success {
-                    min_total_sr_time,
-                    min_total_solver_time,
-                    nb_solutions,
-                } => match mode {
-                    Mode::SRTime => println!("MIN_SR {}", min_total_sr_time),
-                    Mode::SolverTime => println!("MIN_SOLVER {}", min_total_solver_time),
-                    Mode::NbSolutions => println!("NB_SOLS {}", nb_solutions),
-                },
-                _ => println!("EMPTY"),
-            }
-        }
-    } else {
-        println!("EMPTY");
-    }
-}
-
-enum BestExperimentResult {
+/// Typed outcome of a time/solution query on a single config or on the
+/// virtual best of an experiment.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BestExperimentResult {
     Success {
         min_total_sr_time: f64,
-        min_total_solver_time: f64,
+        min_total_solver_time: Option<f64>,
         nb_solutions: u64,
     },
     Timeout {
         max_time_limit: u64,
     },
+    Memout {
+        max_crash_time: f64,
+    },
+    Crash {
+        max_crash_time: f64,
+    },
     None,
 }
 
-fn get_best_time(config: &ConfigMultiple) -> BestExperimentResult {
-    let mut res_sr = vec![];
-    let mut res_solver = vec![];
-    let mut res_timeout = vec![];
-    let mut res_nb_solutions: u64 = 0;
-    for s in &config.solve_information {
-        match s {
-            SolveInformation::Success {
-                total_sr_time,
-                total_solver_time,
-                nb_solutions,
-                ..
-            } => {
-                res_sr.push(*total_sr_time);
-                res_solver.push(*total_solver_time);
-                if res_nb_solutions == 0 {
-                    res_nb_solutions = *nb_solutions;
-                }
-            }
-            SolveInformation::Timeout { time_limit, .. } => {
-                res_timeout.push(*time_limit);
-            }
-            _ => (),
-        }
-    }
-    // get min
-    if !res_sr.is_empty() {
-        res_sr.f_sort();
-        res_solver.f_sort();
-        BestExperimentResult::Success {
-            min_total_sr_time: res_sr[0],
-            min_total_solver_time: res_solver[0],
-            nb_solutions: res_nb_solutions,
-        }
-    } else if !res_timeout.is_empty() {
-        res_timeout.f_sort();
-        BestExperimentResult::Timeout {
-            max_time_limit: res_timeout[res_timeout.len() - 1],
-        }
-    } else {
-        BestExperimentResult::None
+impl ConfigMultiple {
+    /// Best result over all runs of this config.
+    pub fn best_time(&self) -> BestExperimentResult {
+        best_of(
+            &self
+                .solve_information
+                .iter()
+                .map(|s| Run::from_solve("", s))
+                .collect::<Vec<_>>(),
+        )
     }
 }
 
-fn get_virtual_best(configs: &HashMap<String, Box<ConfigMultiple>>) -> BestExperimentResult {
-    let mut v_res_sr = vec![];
-    let mut v_res_solver = vec![];
-    let mut res_nb_solutions: u64 = 0;
-    for v in configs.values() {
-        if let BestExperimentResult::Success {
-            min_total_sr_time,
-            min_total_solver_time,
-            nb_solutions,
-        } = get_best_time(&*v)
-        {
-            v_res_sr.push(min_total_sr_time);
-            v_res_solver.push(min_total_solver_time);
-            if res_nb_solutions == 0 {
-                res_nb_solutions = nb_solutions;
-            }
-        }
-    }
-    // get min
-    if !v_res_sr.is_empty() {
-        v_res_sr.f_sort();
-        v_res_solver.f_sort();
-        BestExperimentResult::Success {
-            min_total_sr_time: v_res_sr[0],
-            min_total_solver_time: v_res_solver[0],
-            nb_solutions: res_nb_solutions,
-        }
-    } else {
-        BestExperimentResult::None
+impl ExperimentMultiple {
+    /// Virtual best over all configs of this experiment.
+    pub fn virtual_best(&self) -> BestExperimentResult {
+        best_of(&self.runs())
     }
 }
+
 pub fn fix_doubts(store: &mut ExperimentStore) {
     for (exp_id, exps) in &mut store.experiments {
         // decide which one to trust
@@ -399,17 +700,15 @@ pub fn fix_doubts(store: &mut ExperimentStore) {
                             total_sr_time,
                             seed,
                             ..
-                        } => {
-                            if nb_solutions != &max_voted_sols[0] {
-                                println!("Change a previous SUCCESS to CRASHED: details: exp: {} config: {} nb: {}, votes: {:?}",exp_id, c_id, *nb_solutions, sol_vote);
-                                *solve = SolveInformation::Crash {
-                                    seed: *seed,
-                                    memory_limit: *memory_limit,
-                                    machine_info: machine_info.clone(),
-                                    time_limit: Some(*time_limit),
-                                    crash_time: *total_sr_time,
-                                };
-                            }
+                        } if nb_solutions != &max_voted_sols[0] => {
+                            println!("Change a previous SUCCESS to CRASHED: details: exp: {} config: {} nb: {}, votes: {:?}",exp_id, c_id, *nb_solutions, sol_vote);
+                            *solve = SolveInformation::Crash {
+                                seed: *seed,
+                                memory_limit: *memory_limit,
+                                machine_info: machine_info.clone(),
+                                time_limit: Some(*time_limit),
+                                crash_time: *total_sr_time,
+                            };
                         }
                         SolveInformation::Doubted {
                             total_solver_time,
@@ -423,23 +722,21 @@ pub fn fix_doubts(store: &mut ExperimentStore) {
                             level_info,
                             freq_nb_solutions,
                             ..
-                        } => {
-                            if nb_solutions == &max_voted_sols[0] {
-                                println!("Change a previous DOUBTED to SUCCESS: details: exp: {} config: {} nb: {}, votes: {:?}",exp_id, c_id, *nb_solutions, sol_vote);
-                                *solve = SolveInformation::Success {
-                                    total_solver_time: *total_solver_time,
-                                    total_sr_time: *total_sr_time,
-                                    total_nodes: *total_nodes,
-                                    nb_solutions: *nb_solutions,
-                                    seed: *seed,
-                                    memory_limit: *memory_limit,
-                                    time_limit: *time_limit,
-                                    machine_info: machine_info.clone(),
-                                    // I don't like this clone
-                                    level_info: level_info.clone(),
-                                    freq_nb_solutions: *freq_nb_solutions,
-                                };
-                            }
+                        } if nb_solutions == &max_voted_sols[0] => {
+                            println!("Change a previous DOUBTED to SUCCESS: details: exp: {} config: {} nb: {}, votes: {:?}",exp_id, c_id, *nb_solutions, sol_vote);
+                            *solve = SolveInformation::Success {
+                                total_solver_time: *total_solver_time,
+                                total_sr_time: *total_sr_time,
+                                total_nodes: *total_nodes,
+                                nb_solutions: *nb_solutions,
+                                seed: *seed,
+                                memory_limit: *memory_limit,
+                                time_limit: *time_limit,
+                                machine_info: machine_info.clone(),
+                                // I don't like this clone
+                                level_info: level_info.clone(),
+                                freq_nb_solutions: *freq_nb_solutions,
+                            };
                         }
                         _ => (),
                     }
@@ -450,45 +747,460 @@ pub fn fix_doubts(store: &mut ExperimentStore) {
 }
 
 impl ExperimentStore {
-    pub fn folder_dump(&self, folder_name: &str, compress: bool) {
-        let suffix = if compress {
-            format!("{}{}", utils::JSON_SUFFIX, utils::ZST_SUFFIX)
-        } else {
-            utils::JSON_SUFFIX.to_string()
+    /// Zstd dictionary trained on the json of every experiment, None if the
+    /// store is too small for one.
+    pub fn train_dictionary(&self) -> Option<Vec<u8>> {
+        let samples: Vec<String> = self
+            .experiments
+            .par_iter()
+            .map(|(_, e)| serde_json::to_string(&e).unwrap())
+            .collect();
+        utils::train_dictionary(&samples)
+    }
+
+    /// Writes one file per experiment, spread over the rayon thread pool.
+    /// An older file of the same experiment with another compression is
+    /// removed. A zstd dictionary is stored in the folder next to the files
+    /// compressed with it.
+    pub fn folder_dump(
+        &self,
+        folder_name: &str,
+        compression: Option<Compression>,
+        dictionary: Option<&[u8]>,
+    ) {
+        let suffix = match compression {
+            Some(c) => format!("{}{}", utils::JSON_SUFFIX, c.codec.suffix()),
+            None => utils::JSON_SUFFIX.to_string(),
         };
-        for (id, e) in &self.experiments {
+        let others: Vec<String> = utils::json_suffixes()
+            .into_iter()
+            .filter(|s| *s != suffix)
+            .collect();
+        let mut path = std::env::current_dir().unwrap();
+        path.push(folder_name);
+        if let Some(_m) = fs::metadata(path).ok().filter(|m| m.is_dir()) {
+            // nothing
+        } else {
+            std::fs::create_dir(folder_name).unwrap();
+        }
+        if let Some(dictionary) = dictionary {
+            utils::write_dictionary(folder_name, dictionary);
+        }
+        self.experiments.par_iter().for_each(|(id, e)| {
             let s = serde_json::to_string(&e).unwrap();
-            let mut path = std::env::current_dir().unwrap();
-            path.push(folder_name);
-            if let Some(_m) = fs::metadata(path).ok().filter(|m| m.is_dir()) {
-                // nothing
-            } else {
-                std::fs::create_dir(folder_name).unwrap();
+            let path = format!("{}/{}{}", folder_name, id, suffix);
+            utils::write_to_file_with_dictionary(&path, s, compression, dictionary);
+            for other_suffix in &others {
+                let other = format!("{}/{}{}", folder_name, id, other_suffix);
+                if Path::new(&other).is_file() {
+                    fs::remove_file(other).expect("Unable to delete old experiment file");
+                }
             }
-            utils::write_to_file(&format!("{}/{}{}", folder_name, id, suffix), s, compress);
+        });
+    }
+
+    /// Reads every experiment file of a folder dump, compressed or not,
+    /// spread over the rayon thread pool. Other files, e.g. leftovers of an
+    /// interrupted write, are ignored.
+    pub fn from_folder(folder_name: &str) -> Self {
+        let dictionaries = utils::read_dictionaries(folder_name);
+        let mut path = std::env::current_dir().unwrap();
+        path.push(folder_name);
+        let mut files: Vec<String> = fs::read_dir(path)
+            .unwrap()
+            .map(|f| f.unwrap().path().display().to_string())
+            .filter(|f| utils::strip_json_suffix(f).is_some())
+            .collect();
+        files.sort();
+        let experiments = files
+            .par_iter()
+            .map(|filename| {
+                let exp_multiple: ExperimentMultiple = serde_json::from_reader(
+                    utils::open_file_with_dictionaries(filename, &dictionaries),
+                )
+                .unwrap();
+                let name = filename.split('/').next_back().unwrap();
+                let exp_id = utils::strip_json_suffix(name).unwrap().to_string();
+                (exp_id, Box::new(exp_multiple))
+            })
+            .collect();
+        ExperimentStore { experiments }
+    }
+
+    /// Writes the experiment files of a folder dump into one tar archive
+    /// instead, in id order.
+    pub fn archive_dump(&self, path: &str, compression: Option<Compression>) {
+        let mut entries: Vec<(String, String)> = self
+            .experiments
+            .par_iter()
+            .map(|(id, e)| {
+                let name = format!("{}{}", id, utils::JSON_SUFFIX);
+                (name, serde_json::to_string(&e).unwrap())
+            })
+            .collect();
+        entries.sort();
+        utils::write_archive(path, &entries, compression);
+    }
+
+    /// Reads a folder dump archived by `archive_dump`, without unpacking it.
+    pub fn from_archive(path: &str) -> Self {
+        Self::from_archive_files(path, |_| true)
+    }
+
+    /// Same as `from_archive`, only parsing the files of the given
+    /// experiments.
+    pub fn from_archive_only(path: &str, exp_ids: &[String]) -> Self {
+        let exp_ids: HashSet<&str> = exp_ids.iter().map(|e| e.as_str()).collect();
+        Self::from_archive_files(path, |id| exp_ids.contains(id))
+    }
+
+    fn from_archive_files<F: Fn(&str) -> bool>(path: &str, keep: F) -> Self {
+        let exp_id = |name: &str| -> Option<String> {
+            let file_name = name.split('/').next_back().unwrap();
+            utils::strip_json_suffix(file_name).map(|id| id.to_string())
+        };
+        let files = utils::read_archive(path, |name| exp_id(name).is_some_and(|id| keep(&id)));
+        let experiments = files
+            .par_iter()
+            .map(|(name, text)| {
+                let exp_multiple: ExperimentMultiple = serde_json::from_str(text).unwrap();
+                (exp_id(name).unwrap(), Box::new(exp_multiple))
+            })
+            .collect();
+        ExperimentStore { experiments }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn success(seed: Option<f64>, sr_time: f64) -> SolveInformation {
+        SolveInformation::Success {
+            total_solver_time: 8.0,
+            total_sr_time: sr_time,
+            total_nodes: Some(100),
+            nb_solutions: 5,
+            seed,
+            memory_limit: 4096,
+            time_limit: 3600,
+            machine_info: "m1".to_string(),
+            level_info: Box::default(),
+            freq_nb_solutions: None,
+        }
+    }
+
+    fn doubted(seed: Option<f64>, sr_time: f64) -> SolveInformation {
+        SolveInformation::Doubted {
+            total_solver_time: 8.0,
+            total_sr_time: sr_time,
+            total_nodes: None,
+            nb_solutions: 6,
+            seed,
+            memory_limit: 4096,
+            time_limit: 3600,
+            machine_info: "m1".to_string(),
+            level_info: Box::default(),
+            freq_nb_solutions: None,
+        }
+    }
+
+    fn timeout(seed: Option<f64>) -> SolveInformation {
+        SolveInformation::Timeout {
+            seed,
+            memory_limit: 4096,
+            time_limit: 3600,
+            machine_info: "m1".to_string(),
+        }
+    }
+
+    fn side_run(solve: SolveInformation) -> ExperimentSingle {
+        ExperimentSingle {
+            model: "mA".to_string(),
+            instance: "i1".to_string(),
+            freq: 0.1,
+            exp_id: "mA_i1_0.1".to_string(),
+            config_id: "c".to_string(),
+            config: Box::new(ConfigMultiple {
+                solve_information: vec![solve],
+                ..Default::default()
+            }),
         }
     }
 
-    pub fn from_folder(folder_name: &str, decompress: bool) -> Self {
-        let mut exp_store = ExperimentStore {
+    fn store_of(runs: Vec<SolveInformation>) -> ExperimentStore {
+        let mut store = ExperimentStore {
             experiments: HashMap::new(),
         };
-        let mut path = std::env::current_dir().unwrap();
-        path.push(folder_name);
-        let files = fs::read_dir(path).unwrap();
-        for f in files {
-            let filename = f.unwrap().path().display().to_string();
-            let s = utils::read_file(&filename, decompress);
-            let exp_multiple: ExperimentMultiple = serde_json::from_str(&s).unwrap();
-            let exp_id = filename
-                .split('/')
-                .last()
-                .unwrap()
-                .trim_end_matches(utils::ZST_SUFFIX)
-                .trim_end_matches(utils::JSON_SUFFIX)
-                .to_string();
-            exp_store.experiments.insert(exp_id, Box::new(exp_multiple));
+        for run in runs {
+            merge_one_experiment(&mut store, side_run(run));
+        }
+        store
+    }
+
+    #[test]
+    fn seeds_and_times_are_compared_by_bits() {
+        assert!(success(Some(0.0), 10.0).same_run(&success(Some(0.0), 10.0)));
+        assert!(!success(Some(-0.0), 10.0).same_run(&success(Some(0.0), 10.0)));
+        assert!(!timeout(Some(-0.0)).same_run(&timeout(Some(0.0))));
+        assert!(!success(Some(1.0), 0.1 + 0.2).same_run(&success(Some(1.0), 0.3)));
+        assert!(!timeout(None).same_run(&timeout(Some(0.0))));
+        assert_eq!(
+            timeout(Some(-0.0)).seed().map(f64::to_bits),
+            Some((-0.0f64).to_bits())
+        );
+    }
+
+    #[test]
+    fn the_run_type_is_compared_except_for_doubted_runs() {
+        let doubted = doubted(Some(1.0), 10.0);
+        assert!(success(Some(1.0), 10.0).same_run(&doubted));
+        assert!(doubted.same_run(&success(Some(1.0), 10.0)));
+        // a retried job finishing after a timeout is a new run
+        assert!(!timeout(Some(1.0)).same_run(&success(Some(1.0), 10.0)));
+        assert!(!success(Some(1.0), 10.0).same_run(&timeout(Some(1.0))));
+        let memout = SolveInformation::Memout {
+            seed: Some(1.0),
+            memory_limit: 4096,
+            time_limit: 3600,
+            machine_info: "m1".to_string(),
+            crash_time: 10.0,
+        };
+        assert!(!memout.same_run(&success(Some(1.0), 10.0)));
+        assert!(!memout.same_run(&timeout(Some(1.0))));
+        let crash = |time_limit| SolveInformation::Crash {
+            seed: Some(1.0),
+            memory_limit: 4096,
+            time_limit,
+            machine_info: "m1".to_string(),
+            crash_time: 10.0,
+        };
+        assert!(!crash(Some(3600)).same_run(&memout));
+        assert!(!crash(None).same_run(&crash(Some(3600))));
+        assert!(crash(None).same_run(&crash(None)));
+    }
+
+    #[test]
+    fn contains_run_finds_the_same_run_of_the_same_config() {
+        let store = store_of(vec![success(Some(1.0), 10.0)]);
+        assert!(contains_run(&store, &side_run(success(Some(1.0), 10.0))));
+        assert!(!contains_run(&store, &side_run(timeout(Some(1.0)))));
+        assert!(!contains_run(&store, &side_run(success(Some(-0.0), 10.0))));
+        let mut other_config = side_run(success(Some(1.0), 10.0));
+        other_config.config_id = "d".to_string();
+        assert!(!contains_run(&store, &other_config));
+    }
+
+    #[test]
+    fn dedupe_keeps_one_copy_of_repeated_runs() {
+        let runs = vec![
+            doubted(Some(1.0), 10.0),
+            timeout(Some(1.0)),
+            success(Some(1.0), 10.0),
+            success(Some(0.0), 10.0),
+            success(Some(-0.0), 10.0),
+            success(Some(0.0), 10.0),
+        ];
+        let config = ConfigMultiple {
+            solve_information: runs,
+            ..Default::default()
+        };
+        let configs = HashMap::from([("c".to_string(), Box::new(config))]);
+        let e = ExperimentMultiple::new("mA".to_string(), "i1".to_string(), 0.1, configs);
+        let mut store = ExperimentStore {
+            experiments: HashMap::from([("mA_i1_0.1".to_string(), Box::new(e))]),
+        };
+        assert_eq!(dedupe(&mut store, None), 2);
+        let runs = &store.experiments["mA_i1_0.1"].configs["c"].solve_information;
+        assert_eq!(runs.len(), 4);
+        // the SUCCESS copy replaces the DOUBTED one in place
+        assert!(matches!(runs[0], SolveInformation::Success { .. }));
+        assert!(matches!(runs[1], SolveInformation::Timeout { .. }));
+        assert_eq!(dedupe(&mut store, None), 0);
+    }
+
+    #[test]
+    fn a_success_after_a_timeout_is_merged() {
+        let mut store = store_of(vec![timeout(None)]);
+        merge_experiments(&mut store, vec![side_run(success(None, 10.0))]);
+        let runs = &store.experiments["mA_i1_0.1"].configs["c"].solve_information;
+        assert_eq!(runs.len(), 2);
+    }
+
+    #[test]
+    fn merging_a_side_file_twice_adds_its_run_once() {
+        let mut store = store_of(vec![]);
+        merge_experiments(&mut store, vec![side_run(success(Some(1.0), 10.0))]);
+        merge_experiments(&mut store, vec![side_run(success(Some(1.0), 10.0))]);
+        let runs = &store.experiments["mA_i1_0.1"].configs["c"].solve_information;
+        assert_eq!(runs.len(), 1);
+    }
+
+    fn status_of(store: &ExperimentStore) -> Vec<&'static str> {
+        store.experiments["mA_i1_0.1"].configs["c"]
+            .solve_information
+            .iter()
+            .map(|s| s.identity().0)
+            .collect()
+    }
+
+    #[test]
+    fn conflicting_experiment_metadata_keeps_the_first_store() {
+        let mut store = store_of(vec![success(Some(1.0), 10.0)]);
+        let mut other = store_of(vec![timeout(Some(2.0))]);
+        let e = other.experiments.get_mut("mA_i1_0.1").unwrap();
+        e.model = "mX".to_string();
+        e.freq = 0.2;
+        assert_eq!(merge_stores(&mut store, other), (1, 0, 2));
+        let e = &store.experiments["mA_i1_0.1"];
+        assert_eq!((e.model.as_str(), e.freq), ("mA", 0.1));
+        assert_eq!(status_of(&store), ["SUCCESS", "TIMEOUT"]);
+    }
+
+    #[test]
+    fn conflicting_config_metadata_keeps_the_first_store() {
+        let mut store = store_of(vec![success(Some(1.0), 10.0)]);
+        let mut other = store_of(vec![success(Some(1.0), 10.0), timeout(Some(2.0))]);
+        let c = other
+            .experiments
+            .get_mut("mA_i1_0.1")
+            .unwrap()
+            .configs
+            .get_mut("c")
+            .unwrap();
+        c.solver = "cplex".to_string();
+        c.mdd = true;
+        assert_eq!(merge_stores(&mut store, other), (1, 1, 2));
+        let c = &store.experiments["mA_i1_0.1"].configs["c"];
+        assert_eq!((c.solver.as_str(), c.mdd), ("", false));
+        assert_eq!(c.solve_information.len(), 2);
+    }
+
+    #[test]
+    fn a_run_doubted_in_one_store_is_the_same_run_in_the_other() {
+        let mut store = store_of(vec![success(Some(1.0), 10.0)]);
+        let other = store_of(vec![doubted(Some(1.0), 10.0)]);
+        assert_eq!(merge_stores(&mut store, other), (0, 1, 0));
+        assert!(matches!(
+            store.experiments["mA_i1_0.1"].configs["c"].solve_information[..],
+            [SolveInformation::Success { .. }]
+        ));
+
+        // the first store is kept the other way round too
+        let mut store = store_of(vec![doubted(Some(1.0), 10.0)]);
+        let other = store_of(vec![success(Some(1.0), 10.0)]);
+        assert_eq!(merge_stores(&mut store, other), (0, 1, 0));
+        assert!(matches!(
+            store.experiments["mA_i1_0.1"].configs["c"].solve_information[..],
+            [SolveInformation::Doubted { .. }]
+        ));
+    }
+
+    /// A store large enough to train a dictionary on.
+    fn many_experiments(model: &str) -> ExperimentStore {
+        let mut store = store_of(vec![]);
+        for i in 0..500 {
+            let mut run = side_run(success(Some(i as f64), 10.0 + i as f64));
+            run.model = model.to_string();
+            run.exp_id = format!("{}_i{}_0.1", model, i);
+            merge_one_experiment(&mut store, run);
+        }
+        store
+    }
+
+    fn dump_with_dictionary(name: &str) -> (ExperimentStore, String) {
+        let store = many_experiments("mA");
+        let dictionary = store.train_dictionary().unwrap();
+        let dir = utils::test_dir(name);
+        store.folder_dump(&dir, Some(Compression::default()), Some(&dictionary));
+        (store, dir)
+    }
+
+    fn dictionary_files(dir: &str) -> Vec<std::path::PathBuf> {
+        fs::read_dir(dir)
+            .unwrap()
+            .map(|f| f.unwrap().path())
+            .filter(|p| p.display().to_string().ends_with(utils::DICT_SUFFIX))
+            .collect()
+    }
+
+    #[test]
+    fn a_dump_compressed_with_a_dictionary_reads_back() {
+        let (store, dir) = dump_with_dictionary("dictionary");
+        assert_eq!(dictionary_files(&dir).len(), 1);
+        assert_eq!(
+            serde_json::to_value(ExperimentStore::from_folder(&dir)).unwrap(),
+            serde_json::to_value(&store).unwrap()
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "needs the zstd dictionary")]
+    fn a_dump_without_its_dictionary_is_refused() {
+        let (_, dir) = dump_with_dictionary("dictionary-missing");
+        for f in dictionary_files(&dir) {
+            fs::remove_file(f).unwrap();
+        }
+        ExperimentStore::from_folder(&dir);
+    }
+
+    #[test]
+    #[should_panic(expected = "needs the zstd dictionary")]
+    fn a_dump_with_another_dictionary_is_refused() {
+        let (_, dir) = dump_with_dictionary("dictionary-wrong");
+        for f in dictionary_files(&dir) {
+            fs::remove_file(f).unwrap();
+        }
+        let other = many_experiments("mZ").train_dictionary().unwrap();
+        utils::write_dictionary(&dir, &other);
+        ExperimentStore::from_folder(&dir);
+    }
+
+    #[test]
+    fn a_folder_may_mix_codecs() {
+        let store = many_experiments("mA");
+        let dir = utils::test_dir("mixed-codecs");
+        let codecs = [
+            None,
+            Some(utils::Codec::Zstd),
+            Some(utils::Codec::Gzip),
+            Some(utils::Codec::Xz),
+        ];
+        for (i, (exp_id, e)) in store.experiments.iter().enumerate() {
+            let compression = codecs[i % codecs.len()].map(|codec| Compression {
+                codec,
+                ..Default::default()
+            });
+            let path = format!("{}/{}{}", dir, exp_id, utils::JSON_SUFFIX);
+            utils::write_to_file(&path, serde_json::to_string(e).unwrap(), compression);
+        }
+        assert_eq!(
+            serde_json::to_value(ExperimentStore::from_folder(&dir)).unwrap(),
+            serde_json::to_value(&store).unwrap()
+        );
+    }
+
+    #[test]
+    fn reading_only_some_experiments_matches_a_full_parse() {
+        for store in [
+            many_experiments("mA"),
+            crate::db::table_tests::sample_store(),
+        ] {
+            let text = serde_json::to_string_pretty(&store).unwrap();
+            let mut exp_ids: Vec<String> = store.experiments.keys().take(3).cloned().collect();
+            exp_ids.push("mZ_i9_1".to_string());
+            let only = ExperimentStore::from_reader_only(text.as_bytes(), &exp_ids);
+
+            let mut full: ExperimentStore = serde_json::from_str(&text).unwrap();
+            full.experiments.retain(|id, _| exp_ids.contains(id));
+            // every id but the unknown one
+            assert_eq!(only.experiments.len(), exp_ids.len() - 1);
+            assert_eq!(
+                serde_json::to_value(&only).unwrap(),
+                serde_json::to_value(&full).unwrap()
+            );
+            let none = ExperimentStore::from_reader_only(text.as_bytes(), &[]);
+            assert!(none.experiments.is_empty());
         }
-        exp_store
     }
 }
@@ -0,0 +1,24 @@
+//! Result Reader Rust: experiment database maintenance.
+//!
+//! The `rrr` binary is a thin command line wrapper over this crate. Library
+//! users can load an [`ExperimentStore`] and query it directly instead of
+//! scraping the text output of the binary.
+
+pub mod backend;
+pub mod csv_dump;
+pub mod db;
+pub mod filter;
+pub mod indexed;
+pub mod json;
+pub mod plot_helper;
+pub mod postgres;
+pub mod sqlite;
+pub mod utils;
+
+pub use backend::{Query, QueryRecord, ResultBackend, Run, RunStatus};
+pub use filter::Filter;
+pub use json::{
+    BestExperimentResult, ConfigMultiple, ExperimentMultiple, ExperimentSingle, ExperimentStore,
+    LevelInformation, SolveInformation,
+};
+pub use utils::Mode;
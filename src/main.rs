@@ -1,129 +1,886 @@
 mod app;
-mod csv_dump;
-mod db;
-mod json;
-mod plot_helper;
-mod utils;
 
-use json::{ExperimentSingle, ExperimentStore};
-use tokio::runtime::Runtime;
-use utils::Mode;
+use clap::ArgMatches;
+use rrr::backend::{batch_exp_ids, status_name, FolderBackend, ResultBackend};
+use rrr::db::{self, DBKind, ExperimentTable, ImportMode, MySqlBackend};
+use rrr::filter::FilteredBackend;
+use rrr::indexed::{self, IndexedStore};
+use rrr::json::{self, ExperimentMultiple, ExperimentSingle, ExperimentStore};
+use rrr::postgres::PostgresBackend;
+use rrr::sqlite::SqliteBackend;
+use rrr::utils::{self, Codec, Compression, Mode};
+use rrr::{plot_helper, Filter, Query, QueryRecord, RunStatus};
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+/// Format of query answers: the historical text lines, one json record per
+/// line, or csv rows under a header.
+#[derive(Clone, Copy, PartialEq)]
+enum OutputFormat {
+    Text,
+    Json,
+    Csv,
+}
+
+impl OutputFormat {
+    fn of(matches: &ArgMatches) -> Self {
+        match matches
+            .get_one::<String>("output_format")
+            .map(|f| f.as_str())
+        {
+            Some("json") => OutputFormat::Json,
+            Some("csv") => OutputFormat::Csv,
+            _ => OutputFormat::Text,
+        }
+    }
+}
+
+/// Prints a query answer as text. `prefix` distinguishes remote answers
+/// (`DB_`) from local ones. Local answers keep their historical strings: a
+/// local `best-time` only reports a successful virtual best and a local `sol`
+/// does not print the support. A local `time` reports memouts and crashes
+/// like a remote one, see the README.
+fn print_text(query: &Query, record: &QueryRecord, prefix: &str) {
+    let local = prefix.is_empty();
+    match query {
+        Query::BestTime { .. } if local && record.status != Some(RunStatus::Success) => {
+            println!("EMPTY")
+        }
+        Query::Sol { .. } if local => match record.nb_solutions {
+            Some(nb) => println!("NB_SOLS {}", nb),
+            None => println!("EMPTY"),
+        },
+        Query::Time { mode, .. } | Query::BestTime { mode, .. } => match record.status {
+            Some(RunStatus::Success) => match mode {
+                Mode::SRTime => println!("{}MIN_SR {}", prefix, record.sr_time.unwrap()),
+                Mode::SolverTime => match record.solver_time {
+                    Some(t) => println!("{}MIN_SOLVER {}", prefix, t),
+                    None => println!("EMPTY"),
+                },
+                Mode::NbSolutions => {
+                    println!("{}NB_SOLS {}", prefix, record.nb_solutions.unwrap_or(0))
+                }
+            },
+            Some(RunStatus::Timeout) => {
+                println!("{}MAX_TIMEOUT {}", prefix, record.sr_time.unwrap() as u64);
+            }
+            Some(RunStatus::Memout) => {
+                println!("{}MAX_MEMOUT_TIME {}", prefix, record.sr_time.unwrap());
+            }
+            Some(RunStatus::Crash) => {
+                println!("{}MAX_CRASH_TIME {}", prefix, record.sr_time.unwrap());
+            }
+            Some(RunStatus::Doubted) | None => println!("EMPTY"),
+        },
+        Query::Sol { .. } => match (record.nb_solutions, record.support) {
+            (Some(nb), Some(support)) => {
+                println!("{}NB_SOLS {} with support {}", prefix, nb, support)
+            }
+            _ => println!("EMPTY"),
+        },
+        Query::NbSuccess { .. } => {
+            println!("{}NB_SUCCESS_DIFF_SEED {}", prefix, record.seeds.len())
+        }
+    }
+}
+
+/// Prints query answers in the given format. Json and csv records are the
+/// same for local and remote answers.
+fn print_answers(answers: &[(Query, QueryRecord)], format: OutputFormat, prefix: &str) {
+    match format {
+        OutputFormat::Text => {
+            for (query, record) in answers {
+                print_text(query, record, prefix);
+            }
+        }
+        OutputFormat::Json => {
+            for (_, record) in answers {
+                println!("{}", serde_json::to_string(record).unwrap());
+            }
+        }
+        OutputFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(io::stdout());
+            writer
+                .write_record([
+                    "exp_id",
+                    "config_id",
+                    "status",
+                    "sr_time",
+                    "solver_time",
+                    "nb_solutions",
+                    "support",
+                    "seeds",
+                ])
+                .unwrap();
+            let or_empty = |v: Option<String>| v.unwrap_or_default();
+            for (_, r) in answers {
+                let seeds: Vec<String> = r.seeds.iter().map(|s| s.to_string()).collect();
+                writer
+                    .write_record([
+                        r.exp_id.clone(),
+                        or_empty(r.config_id.clone()),
+                        status_name(r.status).to_string(),
+                        or_empty(r.sr_time.map(|t| t.to_string())),
+                        or_empty(r.solver_time.map(|t| t.to_string())),
+                        or_empty(r.nb_solutions.map(|n| n.to_string())),
+                        or_empty(r.support.map(|n| n.to_string())),
+                        seeds.join(";"),
+                    ])
+                    .unwrap();
+            }
+            writer.flush().unwrap();
+        }
+    }
+}
+
+fn time_mode(sub_matches: &ArgMatches) -> Mode {
+    if sub_matches.get_flag("solver_time") {
+        Mode::SolverTime
+    } else {
+        Mode::SRTime
+    }
+}
+
+fn is_query(matches: &ArgMatches) -> bool {
+    matches!(
+        matches.subcommand_name(),
+        Some("time" | "best-time" | "sol" | "nb-success" | "query")
+    )
+}
+
+/// Query of a query subcommand.
+fn query_of(matches: &ArgMatches) -> Query {
+    let (name, sub_matches) = matches.subcommand().unwrap();
+    let exp_id: &String = sub_matches.get_one("experiment_id").unwrap();
+    let exp_id = exp_id.clone();
+    let config_id = || sub_matches.get_one::<String>("config_id").unwrap().clone();
+    match name {
+        "time" => Query::Time {
+            exp_id,
+            config_id: config_id(),
+            mode: time_mode(sub_matches),
+        },
+        "best-time" => Query::BestTime {
+            exp_id,
+            mode: time_mode(sub_matches),
+        },
+        "sol" => Query::Sol { exp_id },
+        _ => Query::NbSuccess {
+            exp_id,
+            config_id: config_id(),
+        },
+    }
+}
+
+/// Queries of a query subcommand, or of every line of `query --batch`.
+fn queries_of(matches: &ArgMatches, mode: &str) -> Vec<Query> {
+    match matches.subcommand() {
+        Some(("query", sub_matches)) => read_batch(sub_matches.get_one("batch"), mode),
+        _ => vec![query_of(matches)],
+    }
+}
+
+/// Reads a batch file, or stdin, with one query per line, see `parse_batch`.
+/// A bad line stops the batch before anything is answered.
+fn read_batch(batch: Option<&String>, mode: &str) -> Vec<Query> {
+    let text = match batch.map(|b| b.as_str()) {
+        None | Some("-") => io::read_to_string(io::stdin()).expect("Unable to read stdin"),
+        Some(file_name) => utils::read_file(file_name),
+    };
+    match parse_batch(&text, mode) {
+        Ok(queries) => queries,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Queries of the lines of a batch. Lines are split like a shell would, so
+/// ids with spaces can be quoted. Empty lines and lines starting with `#` are
+/// skipped. The error names the first bad line.
+fn parse_batch(text: &str, mode: &str) -> Result<Vec<Query>, String> {
+    let parser = app::RRRApp::batch_line_parser(mode);
+    let mut queries = vec![];
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let Some(words) = shlex::split(line) else {
+            return Err(format!(
+                "Line {} of the batch: unbalanced quotes or trailing \\",
+                i + 1
+            ));
+        };
+        match parser.clone().try_get_matches_from(words) {
+            Ok(matches) => queries.push(query_of(&matches)),
+            Err(e) => return Err(format!("Line {} of the batch: {}", i + 1, e)),
+        }
+    }
+    Ok(queries)
+}
+
+/// Answers the query subcommands identically for every backend.
+fn run_queries(
+    backend: &mut dyn ResultBackend,
+    queries: Vec<Query>,
+    matches: &ArgMatches,
+    prefix: &str,
+) {
+    let records = backend.answer_all(&queries);
+    let answers: Vec<(Query, QueryRecord)> = queries.into_iter().zip(records).collect();
+    print_answers(&answers, OutputFormat::of(matches), prefix);
+}
+
+/// Filter given with `--filter`, exits on a malformed one.
+fn filter_of(matches: &ArgMatches) -> Option<Filter> {
+    let source = matches.get_one::<String>("filter")?;
+    match Filter::parse(source) {
+        Ok(filter) => Some(filter),
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Runs read from the db have no config metadata or run fields, a filter
+/// using them would select nothing.
+fn check_db_filter(filter: Option<&Filter>) {
+    if filter.is_some_and(|f| f.uses_metadata()) {
+        eprintln!(
+            "Only exp_id, config_id, model, instance and freq can filter runs read from the db."
+        );
+        std::process::exit(1);
+    }
+}
+
+fn to_json<T: Serialize>(value: &T, pretty: bool) -> String {
+    if pretty {
+        serde_json::to_string_pretty(value).unwrap()
+    } else {
+        serde_json::to_string(value).unwrap()
+    }
+}
+
+fn load_store(file_name: &str, folder: bool) -> ExperimentStore {
+    if utils::is_archive(file_name) {
+        ExperimentStore::from_archive(file_name)
+    } else if folder {
+        ExperimentStore::from_folder(file_name)
+    } else if file_name.ends_with(utils::RRRS_SUFFIX) {
+        IndexedStore::open(file_name).to_store()
+    } else {
+        ExperimentStore::from_reader(utils::open_file(file_name))
+    }
+}
+
+/// Writes a store as json, in the indexed format for a `.rrrs` output, or as
+/// an archived folder dump for a `.tar` output. An indexed store only takes
+/// the level of `compression`.
+fn save_store(
+    store: &ExperimentStore,
+    output_file: &str,
+    pretty: bool,
+    compression: Option<Compression>,
+) {
+    if output_file.ends_with(utils::RRRS_SUFFIX) {
+        indexed::write_indexed(store, output_file, compression.unwrap_or_default().level());
+    } else if utils::is_archive(output_file) {
+        store.archive_dump(output_file, compression);
+    } else {
+        utils::write_to_file(output_file, to_json(store, pretty), compression);
+    }
+}
+
+/// Compression of an output. Set by `--compress`, `--codec`, `--level`,
+/// `--long` or a compressed suffix of the output; the codec is the one given,
+/// else the one of the suffix, else `current`, else zstd. `current` is the
+/// codec already used by the output, which also turns compression on.
+fn compression(
+    sub_matches: &ArgMatches,
+    output_file: &str,
+    current: Option<Codec>,
+) -> Option<Compression> {
+    let codec = sub_matches
+        .get_one::<String>("codec")
+        .map(|c| Codec::from_name(c).unwrap());
+    let from_suffix = Codec::from_path(output_file);
+    let level = sub_matches.get_one::<i32>("level").copied();
+    let long = sub_matches.get_flag("long");
+    let compress = sub_matches.get_flag("compress")
+        || codec.is_some()
+        || level.is_some()
+        || long
+        || from_suffix.is_some()
+        || current.is_some();
+    let codec = codec.or(from_suffix).or(current).unwrap_or(Codec::Zstd);
+    if long && codec != Codec::Zstd {
+        eprintln!(
+            "--long is only supported by zstd, {} is compressed with {}.",
+            output_file,
+            codec.name()
+        );
+        std::process::exit(2);
+    }
+    let compression = Compression { codec, level, long };
+    if let Err(e) = compression.check() {
+        eprintln!("{}", e);
+        std::process::exit(2);
+    }
+    let indexed = output_file.ends_with(utils::RRRS_SUFFIX)
+        || matches!(sub_matches.try_get_one::<String>("to"), Ok(Some(to)) if to == "indexed");
+    if indexed && (codec != Codec::Zstd || long) {
+        eprintln!(
+            "{} is an indexed store, its blocks are compressed with zstd and without --long; only --level applies.",
+            output_file
+        );
+        std::process::exit(2);
+    }
+    compress.then_some(compression)
+}
+
+/// Refuses `--long` with gzip or xz before the subcommand does any work.
+fn check_compression(matches: &ArgMatches) {
+    if let Some((_, sub_matches)) = matches.subcommand() {
+        if let (Ok(Some(output)), Ok(Some(_))) = (
+            sub_matches.try_get_one::<String>("output"),
+            sub_matches.try_get_one::<bool>("long"),
+        ) {
+            compression(sub_matches, output, None);
+        }
+    }
+}
+
+/// Opens the SQLite file if given, otherwise the server of the DB conf file.
+fn open_table(
+    sqlite_file: Option<&String>,
+    db_config_file: Option<&String>,
+    postgres: bool,
+) -> Box<dyn ExperimentTable> {
+    match sqlite_file {
+        Some(sqlite_file) => Box::new(SqliteBackend::open(sqlite_file)),
+        None => {
+            let data = utils::read_file(db_config_file.unwrap());
+            let mut db_config = db::DBConfig::from_config_file(&data);
+            if postgres {
+                db_config.kind = DBKind::Postgres;
+            }
+            match db_config.kind {
+                DBKind::MySql => Box::new(MySqlBackend::connect(db_config)),
+                DBKind::Postgres => Box::new(PostgresBackend::connect(db_config)),
+            }
+        }
+    }
+}
+
+/// Rows of the db given to `write --from-db` or `--from-sqlite` selected by
+/// the filter, if any. Db rows have run fields but no config metadata.
+fn db_rows(sub_matches: &ArgMatches, filter: Option<&Filter>) -> Vec<db::DBRow> {
+    let from_sqlite = sub_matches.get_one::<String>("from_sqlite");
+    let from_db = sub_matches.get_one::<String>("from_db");
+    if from_sqlite.is_none() && from_db.is_none() {
+        return vec![];
+    }
+    if filter.is_some_and(|f| f.uses_config_metadata()) {
+        eprintln!("Runs read from the db have no config metadata, only ids and run fields can filter them.");
+        std::process::exit(1);
+    }
+    let mut table = open_table(from_sqlite, from_db, false);
+    if let Err(e) = table.check_schema() {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+    let mut rows = table.all_rows();
+    if let Some(filter) = filter {
+        rows.retain(|r| filter.matches_row(r));
+    }
+    rows
+}
+
+fn write(file_name: &str, folder: bool, sub_matches: &ArgMatches, filter: Option<&Filter>) {
+    let pretty = sub_matches.get_flag("pretty");
+    let maintenance = sub_matches.get_flag("maintenance");
+    let output_file: &String = sub_matches.get_one("output").unwrap();
+    let input_files: Vec<&str> = sub_matches
+        .get_many::<String>("add")
+        .map_or_else(Vec::new, |t| t.map(|f| f.as_str()).collect());
+    // only side files whose runs are all selected are merged and consumed
+    let mut side_runs = vec![];
+    let mut consumed = vec![];
+    for f in &input_files {
+        let runs = json::read_side_files(&[f]);
+        if filter.is_some_and(|filter| !runs.iter().all(|e| filter.matches_single(e))) {
+            println!(
+                "Leaving {} in place, the filter does not select all of its runs.",
+                f
+            );
+            continue;
+        }
+        side_runs.extend(runs);
+        consumed.push(*f);
+    }
+    let rows = db_rows(sub_matches, filter);
+    // a folder dump written onto itself only rewrites the experiments that
+    // get new runs, maintenance still needs all of them. An archive is
+    // always rewritten whole.
+    let in_place =
+        folder && !utils::is_archive(file_name) && utils::same_path(file_name, output_file);
+    let mut store = if in_place && !maintenance {
+        let exp_ids: BTreeSet<&str> = side_runs
+            .iter()
+            .map(|e| e.exp_id.as_str())
+            .chain(rows.iter().map(|r| r.exp_id.as_str()))
+            .collect();
+        FolderBackend::new(file_name).load_store(exp_ids)
+    } else {
+        load_store(file_name, folder)
+    };
+    let nb_runs = |e: &ExperimentMultiple| -> usize {
+        e.configs.values().map(|c| c.solve_information.len()).sum()
+    };
+    let before: HashMap<String, usize> = store
+        .experiments
+        .iter()
+        .map(|(id, e)| (id.clone(), nb_runs(e)))
+        .collect();
+    json::merge_experiments(&mut store, side_runs);
+    if !rows.is_empty() {
+        let counts = db::merge_rows(&mut store, rows, sub_matches.get_flag("add_db_configs"));
+        println!(
+            "Merged {} runs from the db, skipped {} already in the store.",
+            counts.merged, counts.skipped
+        );
+        if counts.new_configs > 0 {
+            println!(
+                "Left out {} runs of configs the store does not have, --add-db-configs merges them with empty metadata.",
+                counts.new_configs
+            );
+        }
+        if counts.incomplete > 0 {
+            println!(
+                "Left out {} rows written before the table was upgraded, they lack the fields of a run.",
+                counts.incomplete
+            );
+        }
+    }
+    if maintenance {
+        json::fix_doubts(&mut store);
+    } else if in_place {
+        // merging only adds runs, experiments whose runs were all skipped
+        // keep their files untouched
+        store
+            .experiments
+            .retain(|id, e| before.get(id) != Some(&nb_runs(e)));
+    }
+    if in_place {
+        // files are compressed like the ones already in the folder
+        let current = utils::folder_codec(output_file);
+        let compression = compression(sub_matches, output_file, current);
+        let dictionary = compression
+            .filter(|c| c.codec == Codec::Zstd)
+            .and_then(|_| utils::newest_dictionary(output_file));
+        store.folder_dump(output_file, compression, dictionary.as_deref());
+    } else {
+        let compression = compression(sub_matches, output_file, None);
+        save_store(&store, output_file, pretty, compression);
+    }
+    // side files are only dropped once their runs are on disk
+    let archive = sub_matches.get_one::<String>("archive");
+    utils::remove_or_archive(&consumed, archive.map(|a| a.as_str()));
+}
+
+/// Lock of a path being written, exits if another rrr holds it too long.
+fn lock_or_exit(file_name: &str, timeout: Duration) -> fs::File {
+    match utils::lock_store(file_name, timeout) {
+        Ok(lock) => lock,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Locks every path a local command writes, and the input `write`, `dedupe`
+/// and `remove` rewrite, so that concurrent jobs do not overwrite each other.
+/// Paths are locked in the order of their lock files, two commands writing
+/// each other's input and output cannot wait on one another.
+fn lock_written(json_matches: &ArgMatches, timeout: Duration) -> Vec<fs::File> {
+    let Some((name, sub_matches)) = json_matches.subcommand() else {
+        return vec![];
+    };
+    let mut paths: Vec<&str> = vec![];
+    if matches!(name, "write" | "dedupe" | "remove") {
+        if let Some(input) = json_matches.get_one::<String>("input") {
+            paths.push(input);
+        }
+    }
+    if let Ok(Some(output)) = sub_matches.try_get_one::<String>("output") {
+        paths.push(output);
+    }
+    // a store read and written under another suffix has a single lock
+    paths.sort_by_key(|p| utils::lock_path(p));
+    paths.dedup_by_key(|p| utils::lock_path(p));
+    paths
+        .into_iter()
+        .map(|p| lock_or_exit(p, timeout))
+        .collect()
+}
+
+/// Merges whole stores into the output.
+fn merge(sub_matches: &ArgMatches, filter: Option<&Filter>) {
+    let output_file: &String = sub_matches.get_one("output").unwrap();
+    let mut stores = sub_matches.get_many::<String>("stores").unwrap().map(|f| {
+        let mut store = load_store(f, Path::new(f).is_dir());
+        if let Some(filter) = filter {
+            store.retain_matching(filter);
+        }
+        store
+    });
+    let mut merged_store = stores.next().unwrap();
+    let (mut merged, mut skipped, mut conflicts) = (0, 0, 0);
+    for store in stores {
+        let (m, s, c) = json::merge_stores(&mut merged_store, store);
+        (merged, skipped, conflicts) = (merged + m, skipped + s, conflicts + c);
+    }
+    println!(
+        "Merged {} runs, skipped {} already in the store, {} conflicts.",
+        merged, skipped, conflicts
+    );
+    let compression = compression(sub_matches, output_file, None);
+    save_store(
+        &merged_store,
+        output_file,
+        sub_matches.get_flag("pretty"),
+        compression,
+    );
+}
+
+/// Asks on stdin before a destructive operation.
+fn confirm(warning: &str) -> bool {
+    println!("{} Type 'yes' to continue:", warning);
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer).unwrap();
+    answer.trim() == "yes"
+}
+
+fn import(
+    backend: &mut dyn ExperimentTable,
+    file_name: &str,
+    sub_matches: &ArgMatches,
+    filter: Option<&Filter>,
+) {
+    let mode: &String = sub_matches.get_one("mode").unwrap();
+    let mode = ImportMode::from_name(mode).unwrap();
+    // after `init --drop` there is nothing left to replace
+    let dropped = sub_matches.try_get_one::<bool>("drop").ok().flatten() == Some(&true);
+    if mode == ImportMode::ReplacePerExperiment
+        && !dropped
+        && !sub_matches.get_flag("yes")
+        && !confirm("This deletes the runs in the db of every experiment in the store.")
+    {
+        println!("Aborted, nothing was imported.");
+        std::process::exit(1);
+    }
+    let mut store = load_store(file_name, false);
+    if let Some(filter) = filter {
+        store.retain_matching(filter);
+    }
+    println!("Depending on the number of experiments, this might take a while.");
+    let (inserted, skipped) = backend.import(&store, mode);
+    println!(
+        "Inserted {} runs, skipped {} duplicates.",
+        inserted, skipped
+    );
+}
 
 fn main() {
     let matches = app::RRRApp::get_matches();
     if let Some(json_matches) = matches.subcommand_matches("local") {
-        let file_name = *json_matches.get_one("input").unwrap();
-        // different behaviour depending on file is compressed or folder_dump
-        let decompress = json_matches.get_flag("decompress");
+        // different behaviour depending on file is a folder_dump, compression
+        // is detected from each file
         let folder = json_matches.get_flag("folder");
-        let mut store = match folder {
-            true => ExperimentStore::from_folder(file_name, decompress),
-            false => {
-                let data = utils::read_file(file_name, decompress);
-                serde_json::from_str(data.as_str()).unwrap()
-            }
+        let filter = filter_of(json_matches);
+        check_compression(json_matches);
+        if let Some(jobs) = json_matches.get_one::<usize>("jobs") {
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(*jobs)
+                .build_global()
+                .unwrap();
+        }
+        let lock_timeout: &u64 = json_matches.get_one("lock_timeout").unwrap();
+        let lock_timeout = Duration::from_secs(*lock_timeout);
+        let _locks = lock_written(json_matches, lock_timeout);
+        if let Some(sub_matches) = json_matches.subcommand_matches("merge") {
+            merge(sub_matches, filter.as_ref());
+            return;
+        }
+        let Some(file_name) = json_matches.get_one::<String>("input") else {
+            eprintln!("error: --input <input> is needed by every subcommand but merge");
+            std::process::exit(2);
         };
-        if let Some(sub_matches) = json_matches.subcommand_matches("time") {
-            let experiment_id = *sub_matches.get_one("experiment_id").unwrap();
-            let config_id = *sub_matches.get_one("config_id").unwrap();
-            let mode = if sub_matches.get_flag("sr_time") {
-                Mode::SRTime
-            } else {
-                Mode::SolverTime
-            };
-            json::check_mode(&store, experiment_id, config_id, mode, true);
-        } else if let Some(sub_matches) = json_matches.subcommand_matches("best-time") {
-            let experiment_id = *sub_matches.get_one("experiment_id").unwrap();
-            let mode = if sub_matches.get_flag("sr_time") {
-                Mode::SRTime
+        if is_query(json_matches) {
+            // the store is loaded once for every query: a folder dump only
+            // reads the files of the queried experiments, an indexed store
+            // one block at a time, a single store only keeps the experiments
+            // the queries need, an archive only parses their files
+            let queries = queries_of(json_matches, "local");
+            let exp_ids = batch_exp_ids(&queries);
+            let indexed = file_name.ends_with(utils::RRRS_SUFFIX);
+            let mut backend: Box<dyn ResultBackend> = if indexed && filter.is_none() {
+                Box::new(IndexedStore::open(file_name))
             } else {
-                Mode::SolverTime
+                let mut store = if utils::is_archive(file_name) {
+                    ExperimentStore::from_archive_only(file_name, &exp_ids)
+                } else if folder {
+                    FolderBackend::new(file_name).load_store(exp_ids.iter().map(|e| e.as_str()))
+                } else if indexed {
+                    IndexedStore::open(file_name).load_store(&exp_ids)
+                } else {
+                    ExperimentStore::from_reader_only(utils::open_file(file_name), &exp_ids)
+                };
+                if let Some(filter) = &filter {
+                    store.retain_matching(filter);
+                }
+                Box::new(store)
             };
-            json::check_mode(&store, experiment_id, "", mode, false);
-        } else if let Some(sub_matches) = json_matches.subcommand_matches("sol") {
-            let experiment_id = *sub_matches.get_one("experiment_id").unwrap();
-            json::check_mode(&store, experiment_id, "", Mode::NbSolutions, false);
-        } else if let Some(sub_matches) = json_matches.subcommand_matches("write") {
-            let pretty = sub_matches.get_flag("pretty");
-            let maintenance = sub_matches.get_flag("maintenance");
-            if let Some(t) = sub_matches.get_many("add") {
-                let input_files: Vec<&str> = t.copied().collect();
-                json::merge_mode(&mut store, input_files);
-            }
-            if maintenance {
-                json::fix_doubts(&mut store);
+            run_queries(backend.as_mut(), queries, json_matches, "");
+            return;
+        }
+        if let Some(name @ ("subset" | "remove")) = json_matches.subcommand_name() {
+            if filter.is_none() {
+                eprintln!("{} needs a --filter selecting what to {}.", name, name);
+                std::process::exit(1);
             }
-            let new_json = if pretty {
-                serde_json::to_string_pretty(&store).unwrap()
-            } else {
-                serde_json::to_string(&store).unwrap()
-            };
-            let output_file = *sub_matches.get_one("output").unwrap();
-            // Compressed the file if specified.
-            let compress = sub_matches.get_flag("compress");
-            utils::write_to_file(output_file, new_json, compress);
+        }
+        if let Some(sub_matches) = json_matches.subcommand_matches("write") {
+            write(file_name, folder, sub_matches, filter.as_ref());
+            return;
+        }
+        let mut store = load_store(file_name, folder);
+        // dedupe and remove write the whole store back, the others only the
+        // selection
+        let whole_store = matches!(json_matches.subcommand_name(), Some("dedupe" | "remove"));
+        if let Some(filter) = filter.as_ref().filter(|_| !whole_store) {
+            store.retain_matching(filter);
+        }
+        if let Some(sub_matches) = json_matches.subcommand_matches("dedupe") {
+            let removed = json::dedupe(&mut store, filter.as_ref());
+            println!("Removed {} duplicate runs.", removed);
+            let output_file: &String = sub_matches.get_one("output").unwrap();
+            save_store(
+                &store,
+                output_file,
+                sub_matches.get_flag("pretty"),
+                compression(sub_matches, output_file, None),
+            );
+        } else if let Some(sub_matches) = json_matches.subcommand_matches("subset") {
+            println!("Selected {} experiments.", store.experiments.len());
+            let output_file: &String = sub_matches.get_one("output").unwrap();
+            save_store(
+                &store,
+                output_file,
+                sub_matches.get_flag("pretty"),
+                compression(sub_matches, output_file, None),
+            );
+        } else if let Some(sub_matches) = json_matches.subcommand_matches("remove") {
+            let (experiments, configs, runs) = store.remove_matching(filter.as_ref().unwrap());
+            println!(
+                "Removed {} experiments, {} configs and {} runs.",
+                experiments, configs, runs
+            );
+            let output_file: &String = sub_matches.get_one("output").unwrap();
+            save_store(
+                &store,
+                output_file,
+                sub_matches.get_flag("pretty"),
+                compression(sub_matches, output_file, None),
+            );
         } else if let Some(sub_matches) = json_matches.subcommand_matches("convert") {
             let pretty = sub_matches.get_flag("pretty");
-            let output_file = *sub_matches.get_one("output").unwrap();
+            let output_file: &String = sub_matches.get_one("output").unwrap();
             let plot_store = plot_helper::convert_store_for_plot(&store);
-            let new_json = if pretty {
-                serde_json::to_string_pretty(&plot_store).unwrap()
-            } else {
-                serde_json::to_string(&plot_store).unwrap()
-            };
-            utils::write_to_file(output_file, new_json, false);
+            let new_json = to_json(&plot_store, pretty);
+            utils::write_to_file(output_file, new_json, None);
         } else if let Some(sub_matches) = json_matches.subcommand_matches("csv-dump") {
-            let output_file = *sub_matches.get_one("output").unwrap();
+            let output_file: &String = sub_matches.get_one("output").unwrap();
             let table = sub_matches.get_flag("table");
             let data = if table {
                 store.convert_to_csv_table()
             } else {
                 store.convert_to_csv()
             };
-            utils::write_to_file(output_file, data, false);
+            utils::write_to_file(output_file, data, None);
         } else if let Some(sub_matches) = json_matches.subcommand_matches("folder-dump") {
-            let output_folder = *sub_matches.get_one("output").unwrap();
-            let compress = sub_matches.get_flag("compress");
-            store.folder_dump(output_folder, compress);
+            let output_folder: &String = sub_matches.get_one("output").unwrap();
+            let use_dictionary = sub_matches.get_flag("dictionary");
+            // a dictionary needs zstd, which is then the default
+            let current = use_dictionary.then_some(Codec::Zstd);
+            let compression = compression(sub_matches, output_folder, current);
+            let archive = utils::is_archive(output_folder);
+            let dictionary = if use_dictionary {
+                if archive {
+                    eprintln!(
+                        "--dictionary is not needed for archives, they are compressed whole."
+                    );
+                    std::process::exit(1);
+                }
+                if compression.unwrap().codec != Codec::Zstd {
+                    eprintln!("--dictionary only works with the zstd codec.");
+                    std::process::exit(1);
+                }
+                let dictionary = store.train_dictionary();
+                if dictionary.is_none() {
+                    println!("Too few experiments to train a dictionary, compressing without one.");
+                }
+                dictionary
+            } else {
+                None
+            };
+            if archive {
+                store.archive_dump(output_folder, compression);
+            } else {
+                store.folder_dump(output_folder, compression, dictionary.as_deref());
+            }
+        } else if let Some(sub_matches) = json_matches.subcommand_matches("convert-format") {
+            let output: &String = sub_matches.get_one("output").unwrap();
+            let pretty = sub_matches.get_flag("pretty");
+            let compression = compression(sub_matches, output, None);
+            match sub_matches.get_one::<String>("to").map(|t| t.as_str()) {
+                Some("folder") if utils::is_archive(output) => {
+                    store.archive_dump(output, compression)
+                }
+                Some("folder") => store.folder_dump(output, compression, None),
+                Some("indexed") => {
+                    indexed::write_indexed(&store, output, compression.unwrap_or_default().level())
+                }
+                Some("json") => utils::write_to_file(output, to_json(&store, pretty), compression),
+                _ => save_store(&store, output, pretty, compression),
+            }
         }
     } else if let Some(db_matches) = matches.subcommand_matches("remote") {
-        let rt = Runtime::new().unwrap();
-        rt.block_on(async {
-            let db_config_file = *db_matches.get_one("db").unwrap();
-            let data = utils::read_file(db_config_file, false);
-            let db_config = db::DBConfig::from_config_file(&data);
-            let mut conn = db::init_db_connection(db_config).await;
-            if let Some(sub_matches) = db_matches.subcommand_matches("init") {
-                db::drop_table(&mut conn).await;
-                db::create_table(&mut conn).await;
-                if sub_matches.get_flag("input") {
-                    let file_name = *sub_matches.get_one("input").unwrap();
-                    let data = utils::read_file(file_name, false);
-                    let store: ExperimentStore = serde_json::from_str(data.as_str()).unwrap();
-                    println!("Depending on the number of experiments, this might take a while.");
-                    db::populate_db(&mut conn, store).await;
+        check_compression(db_matches);
+        let mut backend = open_table(
+            db_matches.get_one::<String>("sqlite"),
+            db_matches.get_one::<String>("db"),
+            db_matches.get_flag("postgres"),
+        );
+        let filter = filter_of(db_matches);
+        if let Some(sub_matches) = db_matches.subcommand_matches("init") {
+            if sub_matches.get_flag("drop") {
+                if !sub_matches.get_flag("yes")
+                    && !confirm("This deletes every run stored in the db.")
+                {
+                    println!("Aborted, nothing was dropped.");
+                    std::process::exit(1);
                 }
-            } else if let Some(sub_matches) = db_matches.subcommand_matches("sol") {
-                let experiment_id = *sub_matches.get_one("experiment_id").unwrap();
-                db::check_mode(&mut conn, experiment_id, "", Mode::NbSolutions, false).await;
-            } else if let Some(sub_matches) = db_matches.subcommand_matches("time") {
-                let experiment_id = *sub_matches.get_one("experiment_id").unwrap();
-                let config_id = *sub_matches.get_one("config_id").unwrap();
-                db::check_mode(&mut conn, experiment_id, config_id, Mode::SRTime, true).await;
-            } else if let Some(sub_matches) = db_matches.subcommand_matches("best-time") {
-                let experiment_id = *sub_matches.get_one("experiment_id").unwrap();
-                db::check_mode(&mut conn, experiment_id, "", Mode::SRTime, false).await;
-            } else if let Some(sub_matches) = db_matches.subcommand_matches("commit") {
-                let exp_file = *sub_matches.get_one("add").unwrap();
-                let data = utils::read_file(exp_file, false);
-                let experiment: ExperimentSingle = serde_json::from_str(data.as_str()).unwrap();
-                db::commit_to_db(&mut conn, experiment).await;
-            } else if let Some(sub_matches) = db_matches.subcommand_matches("nb-success") {
-                let experiment_id = *sub_matches.get_one("experiment_id").unwrap();
-                let config_id = *sub_matches.get_one("config_id").unwrap();
-                let nb = db::check_nb_successful(&mut conn, experiment_id, config_id).await;
-                println!("DB_NB_SUCCESS_DIFF_SEED {}", nb);
-            }
-        });
+                backend.init();
+            } else {
+                backend.migrate();
+            }
+            if let Some(file_name) = sub_matches.get_one::<String>("input") {
+                import(backend.as_mut(), file_name, sub_matches, filter.as_ref());
+            }
+            return;
+        } else if db_matches.subcommand_matches("migrate").is_some() {
+            let from = backend.migrate();
+            println!("Schema version {} -> {}", from, db::SCHEMA_VERSION);
+            return;
+        }
+        // everything else needs an up to date schema
+        if let Err(e) = backend.check_schema() {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        if let Some(sub_matches) = db_matches.subcommand_matches("export") {
+            let output_file: &String = sub_matches.get_one("output").unwrap();
+            let lock_timeout: &u64 = sub_matches.get_one("lock_timeout").unwrap();
+            let _lock = lock_or_exit(output_file, Duration::from_secs(*lock_timeout));
+            let pretty = sub_matches.get_flag("pretty");
+            let compression = compression(sub_matches, output_file, None);
+            check_db_filter(filter.as_ref());
+            let split_ids = sub_matches.get_flag("split_ids");
+            let mut store = backend.export(split_ids);
+            if let Some(filter) = &filter {
+                store.retain_matching_ids(filter);
+            }
+            if split_ids {
+                println!("Config metadata is not in the db, it is left empty. Model, instance and freq come from the experiment ids.");
+            } else {
+                println!("Experiment and config metadata are not in the db; they are left empty.");
+            }
+            save_store(&store, output_file, pretty, compression);
+        } else if let Some(sub_matches) = db_matches.subcommand_matches("import") {
+            let file_name: &String = sub_matches.get_one("input").unwrap();
+            import(backend.as_mut(), file_name, sub_matches, filter.as_ref());
+        } else if let Some(sub_matches) = db_matches.subcommand_matches("commit") {
+            let exp_file: &String = sub_matches.get_one("add").unwrap();
+            let data = utils::read_file(exp_file);
+            let experiment: ExperimentSingle = serde_json::from_str(data.as_str()).unwrap();
+            if filter
+                .as_ref()
+                .is_some_and(|f| !f.matches_single(&experiment))
+            {
+                println!(
+                    "The filter does not select {}, nothing committed.",
+                    exp_file
+                );
+                return;
+            }
+            backend.commit(experiment);
+        } else if is_query(db_matches) {
+            check_db_filter(filter.as_ref());
+            let queries = queries_of(db_matches, "remote");
+            match &filter {
+                Some(filter) => {
+                    let mut filtered = FilteredBackend {
+                        inner: backend.as_mut(),
+                        filter,
+                    };
+                    run_queries(&mut filtered, queries, db_matches, "DB_")
+                }
+                None => run_queries(backend.as_mut(), queries, db_matches, "DB_"),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn batch_lines_are_split_like_a_shell_would() {
+        let text = "# a comment\n\ntime -e 'mA i1 0.1' -c c_kissat -s\n   \n  # indented comment\nsol -e \"mB_i2_0.5\"\n";
+        let queries = parse_batch(text, "local").unwrap();
+        assert_eq!(
+            queries,
+            vec![
+                Query::Time {
+                    exp_id: "mA i1 0.1".to_string(),
+                    config_id: "c_kissat".to_string(),
+                    mode: Mode::SolverTime,
+                },
+                Query::Sol {
+                    exp_id: "mB_i2_0.5".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn a_bad_batch_line_is_named_in_the_error() {
+        let text = "sol -e mA_i1_0.1\ntime -e 'mA_i1_0.1 -c c_kissat\n";
+        assert_eq!(
+            parse_batch(text, "local"),
+            Err("Line 2 of the batch: unbalanced quotes or trailing \\".to_string())
+        );
+        let error = parse_batch("\nbest-time\n", "local").unwrap_err();
+        assert!(error.starts_with("Line 2 of the batch: "), "{}", error);
     }
 }
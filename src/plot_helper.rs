@@ -134,7 +134,7 @@ impl<'a> TempConfigView<'a> {
     }
 }
 
-pub fn convert_store_for_plot(store: &ExperimentStore) -> PlotExperimentStoreView {
+pub fn convert_store_for_plot(store: &ExperimentStore) -> PlotExperimentStoreView<'_> {
     let mut plot_store = HashMap::new();
     for (exp_key, exps) in &store.experiments {
         let mut inner_configs = HashMap::new();
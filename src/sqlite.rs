@@ -0,0 +1,313 @@
+use rusqlite::{params, Connection, Row, Transaction};
+
+use crate::backend::{batch_exp_ids, Query, QueryRecord, ResultBackend, Run, RunCache};
+use crate::db::{attach_levels, DBRow, ExperimentTable, LevelRow, RunKey};
+
+const SELECT_ROWS: &str = "SELECT exp_id, config_id, result_type, measured_time, nb_solutions, machine_info, memory_limit, seed, solver_time, total_nodes, time_limit, freq_nb_solutions, id from experiments";
+
+/// Embedded SQLite version of the remote `experiments` table. Useful when
+/// there is no MySQL server around, e.g. on laptops or in CI.
+pub struct SqliteBackend {
+    conn: Connection,
+}
+
+fn db_row(row: &Row) -> rusqlite::Result<(u64, DBRow)> {
+    let r = DBRow::new(
+        row.get(0)?,
+        row.get(1)?,
+        row.get(2)?,
+        row.get(3)?,
+        row.get(4)?,
+        row.get(5)?,
+        row.get(6)?,
+        row.get(7)?,
+    )
+    .with_details(row.get(8)?, row.get(9)?, row.get(10)?, row.get(11)?);
+    Ok((row.get(12)?, r))
+}
+
+impl SqliteBackend {
+    pub fn open(path: &str) -> Self {
+        let conn = Connection::open(path).expect("Unable to open sqlite db");
+        SqliteBackend { conn }
+    }
+
+    fn query_rows(&self, sql: &str, params: &[&dyn rusqlite::ToSql]) -> Vec<(u64, DBRow)> {
+        let mut stmt = self.conn.prepare(sql).unwrap();
+        let rows = stmt.query_map(params, db_row).unwrap();
+        rows.map(|r| r.unwrap()).collect()
+    }
+
+    fn query_runs(&self, sql: &str, params: &[&dyn rusqlite::ToSql]) -> Vec<Run> {
+        self.query_rows(sql, params)
+            .into_iter()
+            .map(|(_, r)| Run::from(r))
+            .collect()
+    }
+}
+
+impl ExperimentTable for SqliteBackend {
+    fn execute(&mut self, sql: &str) {
+        self.conn.execute_batch(sql).unwrap();
+    }
+
+    fn query_number(&mut self, sql: &str) -> Option<i64> {
+        self.conn
+            .query_row(sql, [], |row| row.get::<_, Option<i64>>(0))
+            .unwrap()
+    }
+
+    fn has_table(&mut self, table: &str) -> bool {
+        self.conn
+            .query_row(
+                "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = ?1",
+                [table],
+                |row| row.get::<_, i64>(0),
+            )
+            .unwrap()
+            > 0
+    }
+
+    fn has_index(&mut self, table: &str, index: &str) -> bool {
+        self.conn
+            .query_row(
+                "SELECT COUNT(*) FROM sqlite_master WHERE type = 'index' AND tbl_name = ?1 AND name = ?2",
+                [table, index],
+                |row| row.get::<_, i64>(0),
+            )
+            .unwrap()
+            > 0
+    }
+
+    fn has_column(&mut self, table: &str, column: &str) -> bool {
+        let mut stmt = self
+            .conn
+            .prepare(&format!("PRAGMA table_info({})", table))
+            .unwrap();
+        let names = stmt.query_map([], |row| row.get::<_, String>(1)).unwrap();
+        let mut found = false;
+        for n in names {
+            found |= n.unwrap() == column;
+        }
+        found
+    }
+
+    fn create_base_table(&mut self) {
+        self.execute(
+            r"CREATE TABLE experiments (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                exp_id VARCHAR(255) NOT NULL,
+                config_id VARCHAR(255) NOT NULL,
+                result_type VARCHAR(255) NOT NULL,
+                measured_time DOUBLE NOT NULL,
+                nb_solutions INTEGER,
+                machine_info VARCHAR(255) NOT NULL,
+                memory_limit INTEGER NOT NULL,
+                seed DOUBLE
+              )
+            ",
+        );
+    }
+
+    fn insert_rows(&mut self, rows: Vec<DBRow>) {
+        // a single transaction, otherwise sqlite syncs to disk for every row
+        let tx = self.conn.transaction().unwrap();
+        insert_into(&tx, rows);
+        tx.commit().unwrap();
+    }
+
+    fn all_rows(&mut self) -> Vec<DBRow> {
+        let rows = self.query_rows(SELECT_ROWS, &[]);
+        let mut stmt = self
+            .conn
+            .prepare("SELECT experiment_id, level, solver_time, nodes, cumulative_nb_solutions, nb_vars, nb_clauses, nb_learnt_clauses from level_info")
+            .unwrap();
+        let levels = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get(0)?,
+                    LevelRow {
+                        level: row.get(1)?,
+                        solver_time: row.get(2)?,
+                        nodes: row.get(3)?,
+                        cumulative_nb_solutions: row.get(4)?,
+                        nb_vars: row.get(5)?,
+                        nb_clauses: row.get(6)?,
+                        nb_learnt_clauses: row.get(7)?,
+                    },
+                ))
+            })
+            .unwrap()
+            .map(|l| l.unwrap())
+            .collect();
+        attach_levels(rows, levels)
+    }
+
+    fn replace_experiment(&mut self, exp_id: &str, rows: Vec<DBRow>) {
+        let tx = self.conn.transaction().unwrap();
+        tx.execute(
+            "DELETE FROM level_info WHERE experiment_id IN (SELECT id FROM experiments WHERE exp_id = ?1)",
+            [exp_id],
+        )
+        .unwrap();
+        tx.execute("DELETE FROM experiments WHERE exp_id = ?1", [exp_id])
+            .unwrap();
+        insert_into(&tx, rows);
+        tx.commit().unwrap();
+    }
+
+    fn run_keys(&mut self, exp_id: &str) -> Vec<RunKey> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT config_id, seed, machine_info from experiments WHERE exp_id = ?1")
+            .unwrap();
+        let keys = stmt
+            .query_map([exp_id], |row| {
+                Ok(RunKey::new(
+                    exp_id,
+                    &row.get::<_, String>(0)?,
+                    row.get(1)?,
+                    &row.get::<_, String>(2)?,
+                ))
+            })
+            .unwrap();
+        keys.map(|k| k.unwrap()).collect()
+    }
+}
+
+impl ResultBackend for SqliteBackend {
+    fn experiment_runs(&mut self, exp_id: &str) -> Vec<Run> {
+        self.query_runs(&format!("{} WHERE exp_id = ?1", SELECT_ROWS), &[&exp_id])
+    }
+
+    fn config_runs(&mut self, exp_id: &str, config_id: &str) -> Vec<Run> {
+        self.query_runs(
+            &format!("{} WHERE exp_id = ?1 and config_id = ?2", SELECT_ROWS),
+            &[&exp_id, &config_id],
+        )
+    }
+
+    fn batch_runs(&mut self, exp_ids: &[String]) -> RunCache {
+        // one query for every experiment, ids passed as a json array to
+        // stay clear of the bound parameter limit
+        let exp_ids = serde_json::to_string(exp_ids).unwrap();
+        let rows = self.query_rows(
+            &format!(
+                "{} WHERE exp_id IN (SELECT value FROM json_each(?1))",
+                SELECT_ROWS
+            ),
+            &[&exp_ids],
+        );
+        RunCache::new(
+            rows.into_iter()
+                .map(|(_, r)| (r.exp_id.clone(), Run::from(r))),
+        )
+    }
+
+    fn answer_all(&mut self, queries: &[Query]) -> Vec<QueryRecord> {
+        self.batch_runs(&batch_exp_ids(queries)).answer_all(queries)
+    }
+}
+
+/// Inserts rows together with their level info in an open transaction.
+fn insert_into(tx: &Transaction, rows: Vec<DBRow>) {
+    let mut stmt = tx
+            .prepare(
+                r"INSERT INTO experiments (exp_id, config_id, result_type, measured_time, nb_solutions, machine_info, memory_limit, seed, solver_time, total_nodes, time_limit, freq_nb_solutions)
+                  VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+            )
+            .unwrap();
+    let mut level_stmt = tx
+            .prepare(
+                r"INSERT INTO level_info (experiment_id, level, solver_time, nodes, cumulative_nb_solutions, nb_vars, nb_clauses, nb_learnt_clauses)
+                  VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            )
+            .unwrap();
+    for r in rows {
+        let id = stmt
+            .insert(params![
+                r.exp_id,
+                r.config_id,
+                r.result_type,
+                r.measured_time,
+                r.nb_solutions,
+                r.machine_info,
+                r.memory_limit,
+                r.seed,
+                r.solver_time,
+                r.total_nodes,
+                r.time_limit,
+                r.freq_nb_solutions,
+            ])
+            .unwrap();
+        if let Some(level_info) = r.level_info {
+            for l in LevelRow::from_level_info(&level_info) {
+                level_stmt
+                    .execute(params![
+                        id,
+                        l.level,
+                        l.solver_time,
+                        l.nodes,
+                        l.cumulative_nb_solutions,
+                        l.nb_vars,
+                        l.nb_clauses,
+                        l.nb_learnt_clauses,
+                    ])
+                    .unwrap();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::table_tests;
+
+    fn in_memory() -> SqliteBackend {
+        SqliteBackend {
+            conn: Connection::open_in_memory().unwrap(),
+        }
+    }
+
+    #[test]
+    fn init_creates_the_current_schema() {
+        table_tests::init_creates_the_current_schema(&mut in_memory());
+    }
+
+    #[test]
+    fn migrate_keeps_rows_of_the_base_table() {
+        table_tests::migrate_keeps_rows_of_the_base_table(&mut in_memory());
+    }
+
+    #[test]
+    fn import_modes() {
+        table_tests::import_modes(&mut in_memory());
+    }
+
+    #[test]
+    fn export_round_trips_runs_and_level_info() {
+        table_tests::export_round_trips_runs_and_level_info(&mut in_memory());
+    }
+
+    #[test]
+    fn commit_adds_one_run() {
+        table_tests::commit_adds_one_run(&mut in_memory());
+    }
+
+    #[test]
+    fn batch_runs_answer_like_single_queries() {
+        table_tests::batch_runs_answer_like_single_queries(&mut in_memory());
+    }
+
+    #[test]
+    fn batch_runs_go_past_the_bound_parameter_limit() {
+        let mut table = in_memory();
+        table.init();
+        let store = table_tests::sample_store();
+        table.import(&store, crate::db::ImportMode::Append);
+        let mut ids: Vec<String> = (0..40000).map(|i| format!("m_i{}_0.1", i)).collect();
+        ids.push("mB_i2_0.5".to_string());
+        assert_eq!(table.batch_runs(&ids).experiment_runs("mB_i2_0.5").len(), 2);
+    }
+}
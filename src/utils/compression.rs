@@ -1,7 +1,133 @@
-use std::io;
+use flate2::bufread::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use std::io::{self, BufRead, Read, Write};
+use std::ops::RangeInclusive;
 use std::{fs, io::Cursor};
+use xz2::bufread::XzDecoder;
+use xz2::write::XzEncoder;
 const COMPRESSION_LEVEL: i32 = 1;
 
+/// First bytes of every zstd frame.
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
+const GZIP_MAGIC: [u8; 2] = [0x1F, 0x8B];
+const XZ_MAGIC: [u8; 6] = [0xFD, 0x37, 0x7A, 0x58, 0x5A, 0x00];
+
+/// Largest trained dictionary, the default of the zstd cli.
+const DICTIONARY_SIZE: usize = 110 * 1024;
+
+/// Window of the zstd long distance mode, 128 MiB. Readers accept it without
+/// any extra setting.
+const LONG_WINDOW_LOG: u32 = 27;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Codec {
+    Zstd,
+    Gzip,
+    Xz,
+}
+
+impl Codec {
+    pub const ALL: [Codec; 3] = [Codec::Zstd, Codec::Gzip, Codec::Xz];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "zstd" => Some(Codec::Zstd),
+            "gzip" => Some(Codec::Gzip),
+            "xz" => Some(Codec::Xz),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Codec::Zstd => "zstd",
+            Codec::Gzip => "gzip",
+            Codec::Xz => "xz",
+        }
+    }
+
+    /// Codec of a file name ending with its suffix.
+    pub fn from_path(path: &str) -> Option<Self> {
+        Codec::ALL.into_iter().find(|c| path.ends_with(c.suffix()))
+    }
+
+    fn from_header(header: &[u8]) -> Option<Self> {
+        if header.starts_with(&ZSTD_MAGIC) {
+            Some(Codec::Zstd)
+        } else if header.starts_with(&GZIP_MAGIC) {
+            Some(Codec::Gzip)
+        } else if header.starts_with(&XZ_MAGIC) {
+            Some(Codec::Xz)
+        } else {
+            None
+        }
+    }
+
+    pub fn suffix(&self) -> &'static str {
+        match self {
+            Codec::Zstd => super::ZST_SUFFIX,
+            Codec::Gzip => super::GZ_SUFFIX,
+            Codec::Xz => super::XZ_SUFFIX,
+        }
+    }
+
+    /// Levels the codec accepts, negative zstd levels included.
+    pub fn levels(&self) -> RangeInclusive<i32> {
+        match self {
+            Codec::Zstd => zstd::compression_level_range(),
+            Codec::Gzip | Codec::Xz => 0..=9,
+        }
+    }
+
+    fn default_level(&self) -> i32 {
+        match self {
+            Codec::Zstd => COMPRESSION_LEVEL,
+            Codec::Gzip | Codec::Xz => 6,
+        }
+    }
+}
+
+/// How files are compressed. `level` falls back to the codec default, `long`
+/// is only accepted with zstd.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Compression {
+    pub codec: Codec,
+    pub level: Option<i32>,
+    pub long: bool,
+}
+
+impl Default for Compression {
+    fn default() -> Self {
+        Compression {
+            codec: Codec::Zstd,
+            level: None,
+            long: false,
+        }
+    }
+}
+
+impl Compression {
+    pub fn level(&self) -> i32 {
+        self.level.unwrap_or_else(|| self.codec.default_level())
+    }
+
+    /// Refuses a level the codec does not have.
+    pub fn check(&self) -> Result<(), String> {
+        let levels = self.codec.levels();
+        if levels.contains(&self.level()) {
+            Ok(())
+        } else {
+            Err(format!(
+                "{} levels go from {} to {}, not {}.",
+                self.codec.name(),
+                levels.start(),
+                levels.end(),
+                self.level()
+            ))
+        }
+    }
+}
+
 pub fn _compress_file_to_file(source: &str) {
     let mut file = fs::File::open(source).unwrap();
     let mut encoder = {
@@ -13,20 +139,67 @@ pub fn _compress_file_to_file(source: &str) {
     encoder.finish().unwrap();
 }
 
-pub fn compress_string_to_file(source: String, source_filename: &str) {
-    let mut encoder = {
-        let target = fs::File::create(
-            source_filename
-                .trim_end_matches(super::ZST_SUFFIX)
-                .to_string()
-                + super::ZST_SUFFIX,
-        )
-        .unwrap();
-        zstd::Encoder::new(target, COMPRESSION_LEVEL).unwrap()
+/// Compresses into exactly `target` and syncs it to disk. The dictionary is
+/// only used by zstd.
+pub fn compress_string_to_file(
+    source: String,
+    target: &str,
+    compression: Compression,
+    dictionary: Option<&[u8]>,
+) {
+    compress_to_file(target, compression, dictionary, |w| {
+        io::copy(&mut Cursor::new(source), w).unwrap();
+    });
+}
+
+/// Same as `compress_string_to_file` for content streamed by `fill` into the
+/// encoder.
+pub fn compress_to_file<F: FnOnce(&mut dyn Write)>(
+    target: &str,
+    compression: Compression,
+    dictionary: Option<&[u8]>,
+    fill: F,
+) {
+    compression.check().unwrap();
+    let target = fs::File::create(target).unwrap();
+    let level = compression.level();
+    let file = match compression.codec {
+        Codec::Zstd => {
+            let mut encoder = match dictionary {
+                Some(dictionary) => zstd::Encoder::with_dictionary(target, level, dictionary),
+                None => zstd::Encoder::new(target, level),
+            }
+            .unwrap();
+            if compression.long {
+                encoder.long_distance_matching(true).unwrap();
+                encoder.window_log(LONG_WINDOW_LOG).unwrap();
+            }
+            fill(&mut encoder);
+            encoder.finish().unwrap()
+        }
+        Codec::Gzip => {
+            let level = flate2::Compression::new(level as u32);
+            let mut encoder = GzEncoder::new(target, level);
+            fill(&mut encoder);
+            encoder.finish().unwrap()
+        }
+        Codec::Xz => {
+            let mut encoder = XzEncoder::new(target, level as u32);
+            fill(&mut encoder);
+            encoder.finish().unwrap()
+        }
     };
-    let mut source_cursor = Cursor::new(source);
-    io::copy(&mut source_cursor, &mut encoder).unwrap();
-    encoder.finish().unwrap();
+    file.sync_all().unwrap();
+}
+
+/// Compresses a block with zstd at `level`.
+pub fn compress_bytes(source: &[u8], level: i32) -> Vec<u8> {
+    zstd::encode_all(source, level).unwrap()
+}
+
+/// Decompresses a zstd block, failing on a damaged one.
+pub fn decompress_bytes(source: &[u8]) -> io::Result<Vec<u8>> {
+    zstd::decode_all(source)
 }
 
 pub fn _decompress_file_to_file(source: &str) {
@@ -38,12 +211,103 @@ pub fn _decompress_file_to_file(source: &str) {
     io::copy(&mut decoder, &mut target).unwrap();
 }
 
-pub fn decompress_file_to_string(source: &str) -> String {
-    let mut decoder = {
-        let file = fs::File::open(source).unwrap();
-        zstd::Decoder::new(file).unwrap()
-    };
-    let mut target_writer = vec![];
-    io::copy(&mut decoder, &mut target_writer).unwrap();
-    String::from_utf8(target_writer).unwrap()
+/// Trains a zstd dictionary on similar small documents. None when there are
+/// too few of them to learn anything.
+pub fn train_dictionary(samples: &[String]) -> Option<Vec<u8>> {
+    zstd::dict::from_samples(samples, DICTIONARY_SIZE).ok()
+}
+
+/// Id written in a trained dictionary and in every frame compressed with it.
+pub fn dictionary_id(dictionary: &[u8]) -> Option<u32> {
+    zstd::zstd_safe::get_dict_id_from_dict(dictionary).map(|id| id.get())
+}
+
+/// Id of the dictionary a zstd frame needs, from its first bytes.
+pub fn frame_dictionary_id(header: &[u8]) -> Option<u32> {
+    zstd::zstd_safe::get_dict_id_from_frame(header).map(|id| id.get())
+}
+
+/// Codec of a file from its name, or else from its first bytes.
+pub fn detect(path: &str, header: &[u8]) -> Option<Codec> {
+    Codec::from_path(path).or_else(|| Codec::from_header(header))
+}
+
+/// Streaming decoder over a buffered compressed reader.
+pub fn decoder<R: BufRead + 'static>(
+    reader: R,
+    codec: Codec,
+    dictionary: Option<&[u8]>,
+) -> Box<dyn Read> {
+    match (codec, dictionary) {
+        (Codec::Zstd, Some(dictionary)) => {
+            Box::new(zstd::Decoder::with_dictionary(reader, dictionary).unwrap())
+        }
+        (Codec::Zstd, None) => Box::new(zstd::Decoder::with_buffer(reader).unwrap()),
+        (Codec::Gzip, _) => Box::new(MultiGzDecoder::new(reader)),
+        (Codec::Xz, _) => Box::new(XzDecoder::new(reader)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::{read_file, test_dir, write_to_file};
+
+    #[test]
+    fn every_codec_reads_back_what_it_wrote() {
+        let dir = test_dir("codecs");
+        let content = r#"{"experiments": {}}"#.repeat(100);
+        for codec in Codec::ALL {
+            let levels = codec.levels();
+            for level in [None, Some(*levels.end()), Some(0.max(*levels.start()))] {
+                let compression = Compression {
+                    codec,
+                    level,
+                    long: false,
+                };
+                let path = format!("{}/store.json", dir);
+                write_to_file(&path, content.clone(), Some(compression));
+                let written = format!("{}{}", path, codec.suffix());
+                assert_eq!(read_file(&written), content, "{:?}", compression);
+            }
+        }
+    }
+
+    #[test]
+    fn levels_out_of_the_codec_range_are_refused() {
+        let with = |codec, level| Compression {
+            codec,
+            level: Some(level),
+            long: false,
+        };
+        assert_eq!(with(Codec::Gzip, 9).check(), Ok(()));
+        assert_eq!(
+            with(Codec::Gzip, 10).check(),
+            Err("gzip levels go from 0 to 9, not 10.".to_string())
+        );
+        assert!(with(Codec::Xz, -1).check().is_err());
+        assert_eq!(with(Codec::Zstd, 19).check(), Ok(()));
+        assert!(with(Codec::Zstd, 23).check().is_err());
+    }
+
+    #[test]
+    fn files_without_a_suffix_are_recognised_by_their_header() {
+        let dir = test_dir("headers");
+        let content = r#"{"experiments": {}}"#.to_string();
+        for codec in Codec::ALL {
+            let path = format!("{}/{}", dir, codec.name());
+            let compression = Compression {
+                codec,
+                ..Default::default()
+            };
+            compress_string_to_file(content.clone(), &path, compression, None);
+            let header = fs::read(&path).unwrap();
+            assert_eq!(detect(&path, &header), Some(codec));
+            assert_eq!(read_file(&path), content);
+        }
+        let plain = format!("{}/plain", dir);
+        fs::write(&plain, &content).unwrap();
+        assert_eq!(detect(&plain, content.as_bytes()), None);
+        assert_eq!(read_file(&plain), content);
+    }
 }
@@ -1,11 +1,20 @@
 mod compression;
 
+pub use compression::{compress_bytes, decompress_bytes, train_dictionary, Codec, Compression};
+
+use fs2::FileExt;
 use num::{FromPrimitive, Zero};
 use std::{
+    collections::HashMap,
     fs,
+    io::{self, BufRead, BufReader, Read, Write},
     ops::{Add, Div},
+    path::Path,
+    thread,
+    time::{Duration, Instant},
 };
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Mode {
     SRTime,
     SolverTime,
@@ -45,17 +54,398 @@ pub const JSON_SUFFIX: &str = ".json";
 
 pub const ZST_SUFFIX: &str = ".zst";
 
-pub fn write_to_file(filepath: &str, content: String, compress: bool) {
-    match compress {
-        true => compression::compress_string_to_file(content, filepath),
-        false => fs::write(filepath, content).expect("Unable to write to file"),
+pub const GZ_SUFFIX: &str = ".gz";
+
+pub const XZ_SUFFIX: &str = ".xz";
+
+/// Suffixes a json store file can have, compressed ones first.
+pub fn json_suffixes() -> Vec<String> {
+    let mut suffixes: Vec<String> = Codec::ALL
+        .iter()
+        .map(|c| format!("{}{}", JSON_SUFFIX, c.suffix()))
+        .collect();
+    suffixes.push(JSON_SUFFIX.to_string());
+    suffixes
+}
+
+/// Name without its json suffix, if it is a json store file.
+pub fn strip_json_suffix(name: &str) -> Option<&str> {
+    json_suffixes()
+        .iter()
+        .find_map(|s| name.strip_suffix(s.as_str()))
+}
+
+pub const RRRS_SUFFIX: &str = ".rrrs";
+
+pub const DICT_SUFFIX: &str = ".dict";
+
+pub const TAR_SUFFIX: &str = ".tar";
+
+/// True for a `.tar` file, compressed or not, e.g. `dump.tar.zst`.
+pub fn is_archive(path: &str) -> bool {
+    let path = Codec::from_path(path).map_or(path, |c| path.trim_end_matches(c.suffix()));
+    path.ends_with(TAR_SUFFIX)
+}
+
+/// Writes named text files into a tar archive, replaced atomically like
+/// `write_to_file`. Entries get fixed metadata so the same content always
+/// gives the same archive.
+pub fn write_archive(
+    filepath: &str,
+    entries: &[(String, String)],
+    compression: Option<Compression>,
+) {
+    let fill = |w: &mut dyn Write| {
+        let mut builder = tar::Builder::new(w);
+        for (name, content) in entries {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_mtime(0);
+            builder
+                .append_data(&mut header, name, content.as_bytes())
+                .expect("Unable to write to archive");
+        }
+        builder.finish().expect("Unable to write to archive");
+    };
+    match compression {
+        Some(compression) => {
+            let suffix = compression.codec.suffix();
+            let target = format!("{}{}", filepath.trim_end_matches(suffix), suffix);
+            let tmp = tmp_path(&target);
+            compression::compress_to_file(&tmp, compression, None, fill);
+            fs::rename(&tmp, &target).expect("Unable to replace file");
+        }
+        None => write_streamed(filepath, fill),
+    }
+}
+
+/// Names and text of the files of a tar archive, compressed or not, for which
+/// `keep` holds. Compressed files inside the archive are decompressed.
+pub fn read_archive<F: Fn(&str) -> bool>(filepath: &str, keep: F) -> Vec<(String, String)> {
+    let mut archive = tar::Archive::new(open_file(filepath));
+    let mut files = vec![];
+    for entry in archive.entries().expect("Unable to read archive") {
+        let mut entry = entry.expect("Unable to read archive");
+        let name = entry.path().unwrap().display().to_string();
+        if !entry.header().entry_type().is_file() || !keep(&name) {
+            continue;
+        }
+        let mut data = vec![];
+        entry
+            .read_to_end(&mut data)
+            .expect("Unable to read archive");
+        let mut text = String::new();
+        match compression::detect(&name, &data) {
+            Some(codec) => compression::decoder(io::Cursor::new(data), codec, None)
+                .read_to_string(&mut text)
+                .expect("Unable to decompress archive file"),
+            None => io::Cursor::new(data)
+                .read_to_string(&mut text)
+                .expect("Archive file is not text"),
+        };
+        files.push((name, text));
+    }
+    files
+}
+
+/// Zstd dictionaries of a folder dump by id.
+pub type Dictionaries = HashMap<u32, Vec<u8>>;
+
+/// Every dictionary stored in a folder dump, there is usually at most one.
+pub fn read_dictionaries(folder: &str) -> Dictionaries {
+    let Ok(entries) = fs::read_dir(folder) else {
+        return Dictionaries::new();
+    };
+    entries
+        .map(|f| f.unwrap().path())
+        .filter(|p| p.display().to_string().ends_with(DICT_SUFFIX))
+        .filter_map(|p| {
+            let dictionary = fs::read(p).expect("Unable to read from file");
+            Some((compression::dictionary_id(&dictionary)?, dictionary))
+        })
+        .collect()
+}
+
+/// Most recently written dictionary of a folder dump, to compress new files
+/// like the others.
+pub fn newest_dictionary(folder: &str) -> Option<Vec<u8>> {
+    let path = fs::read_dir(folder)
+        .ok()?
+        .map(|f| f.unwrap().path())
+        .filter(|p| p.display().to_string().ends_with(DICT_SUFFIX))
+        .max_by_key(|p| p.metadata().and_then(|m| m.modified()).ok())?;
+    Some(fs::read(path).expect("Unable to read from file"))
+}
+
+/// Stores a dictionary in a folder dump as `<id>.dict`. Older dictionaries
+/// are kept for the files still compressed with them.
+pub fn write_dictionary(folder: &str, dictionary: &[u8]) {
+    let id = compression::dictionary_id(dictionary).expect("Not a zstd dictionary");
+    write_bytes_to_file(&format!("{}/{}{}", folder, id, DICT_SUFFIX), dictionary);
+}
+
+/// Writes `content` to `filepath`, with the suffix of the codec added when
+/// compressing. The data goes to a temporary file next to the target which is
+/// then renamed over it, so readers and crashes never see a half written file.
+pub fn write_to_file(filepath: &str, content: String, compression: Option<Compression>) {
+    write_to_file_with_dictionary(filepath, content, compression, None);
+}
+
+/// Same as `write_to_file`, zstd compressing with a trained dictionary.
+pub fn write_to_file_with_dictionary(
+    filepath: &str,
+    content: String,
+    compression: Option<Compression>,
+    dictionary: Option<&[u8]>,
+) {
+    match compression {
+        Some(compression) => {
+            let suffix = compression.codec.suffix();
+            let target = format!("{}{}", filepath.trim_end_matches(suffix), suffix);
+            let tmp = tmp_path(&target);
+            compression::compress_string_to_file(content, &tmp, compression, dictionary);
+            fs::rename(&tmp, &target).expect("Unable to replace file");
+        }
+        None => write_bytes_to_file(filepath, content.as_bytes()),
+    }
+}
+
+/// Same as `write_to_file` for raw bytes, without compression.
+pub fn write_bytes_to_file(filepath: &str, content: &[u8]) {
+    write_streamed(filepath, |w| {
+        w.write_all(content).expect("Unable to write to file")
+    });
+}
+
+/// Same as `write_bytes_to_file` for content streamed by `fill`, so it never
+/// has to be held in memory whole.
+pub fn write_streamed<F: FnOnce(&mut dyn Write)>(filepath: &str, fill: F) {
+    let tmp = tmp_path(filepath);
+    let file = fs::File::create(&tmp).expect("Unable to write to file");
+    let mut writer = io::BufWriter::new(file);
+    fill(&mut writer);
+    let file = writer.into_inner().expect("Unable to write to file");
+    file.sync_all().expect("Unable to write to file");
+    fs::rename(&tmp, filepath).expect("Unable to replace file");
+}
+
+fn tmp_path(target: &str) -> String {
+    format!("{}.{}.tmp", target, std::process::id())
+}
+
+/// Codec of the compressed files of a folder, if it has any.
+pub fn folder_codec(folder: &str) -> Option<Codec> {
+    fs::read_dir(folder).ok()?.find_map(|f| {
+        let name = f.ok()?.file_name().to_string_lossy().to_string();
+        Codec::from_path(&name)
+    })
+}
+
+/// True if both paths exist and lead to the same file or folder.
+pub fn same_path(a: &str, b: &str) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// Lock file of a store path. The codec suffix is left out, so that writers
+/// of `out.json` and `out.json.zst` take the same lock, and the folder is
+/// resolved, so that two spellings of a path do too.
+pub fn lock_path(path: &str) -> String {
+    let path = path.trim_end_matches('/');
+    let path = Codec::from_path(path).map_or(path, |c| path.trim_end_matches(c.suffix()));
+    let path = Path::new(path);
+    let folder = match path.parent() {
+        Some(p) if p.as_os_str().is_empty() => Path::new("."),
+        Some(p) => p,
+        None => Path::new("/"),
+    };
+    let resolved = match (fs::canonicalize(folder), path.file_name()) {
+        (Ok(folder), Some(name)) => folder.join(name),
+        _ => path.to_path_buf(),
+    };
+    format!("{}.lock", resolved.to_string_lossy())
+}
+
+/// Takes an exclusive advisory lock on the `lock_path` of `path`, waiting up
+/// to `timeout` for other rrr processes holding it. The lock is released when
+/// the returned file is dropped. The lock file itself is left behind on
+/// purpose, removing it would let two processes lock different files.
+pub fn lock_store(path: &str, timeout: Duration) -> Result<fs::File, String> {
+    let lock_path = lock_path(path);
+    let file = fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&lock_path)
+        .map_err(|e| format!("Unable to open lock file {}: {}", lock_path, e))?;
+    let start = Instant::now();
+    let mut waiting = false;
+    loop {
+        match file.try_lock_exclusive() {
+            Ok(()) => return Ok(file),
+            Err(e) if e.raw_os_error() == fs2::lock_contended_error().raw_os_error() => {
+                if start.elapsed() >= timeout {
+                    return Err(format!(
+                        "Gave up after {}s waiting for {}, another rrr is updating the store.",
+                        timeout.as_secs(),
+                        lock_path
+                    ));
+                }
+                if !waiting {
+                    println!("Waiting for {}.", lock_path);
+                    waiting = true;
+                }
+                thread::sleep(Duration::from_millis(200));
+            }
+            Err(e) => return Err(format!("Unable to lock {}: {}", lock_path, e)),
+        }
     }
 }
 
-pub fn read_file(filepath: &str, decompress: bool) -> String {
-    match decompress {
-        true => compression::decompress_file_to_string(filepath),
-        false => String::from_utf8(fs::read(filepath).expect("Unable to read from file"))
-            .expect("Unable to read file as utf8"),
+/// Deletes consumed side files, or moves them into `archive` if given. A file
+/// already archived under the same name gets a numbered suffix.
+pub fn remove_or_archive(files: &[&str], archive: Option<&str>) {
+    for f in files {
+        match archive {
+            None => fs::remove_file(f).expect("Unable to delete side file"),
+            Some(dir) => {
+                fs::create_dir_all(dir).expect("Unable to create archive folder");
+                let name = Path::new(f).file_name().unwrap().to_string_lossy();
+                let mut target = Path::new(dir).join(name.as_ref());
+                let mut i = 1;
+                while target.exists() {
+                    target = Path::new(dir).join(format!("{}.{}", name, i));
+                    i += 1;
+                }
+                // rename does not work across file systems
+                if fs::rename(f, &target).is_err() {
+                    fs::copy(f, &target).expect("Unable to archive side file");
+                    fs::remove_file(f).expect("Unable to delete side file");
+                }
+            }
+        }
+    }
+}
+
+/// Buffered reader over a file, decompressing on the fly so that large
+/// stores can be parsed without holding their text in memory. Compressed files
+/// are recognised by their suffix or their first bytes.
+pub fn open_file(filepath: &str) -> Box<dyn Read> {
+    open_file_with_dictionaries(filepath, &Dictionaries::new())
+}
+
+/// Same as `open_file` for files of a folder dump, whose zstd files may need
+/// one of its dictionaries.
+pub fn open_file_with_dictionaries(filepath: &str, dictionaries: &Dictionaries) -> Box<dyn Read> {
+    let mut reader = BufReader::new(fs::File::open(filepath).expect("Unable to read from file"));
+    let header = reader.fill_buf().expect("Unable to read from file");
+    let codec = compression::detect(filepath, header);
+    let dictionary = match codec {
+        Some(Codec::Zstd) => compression::frame_dictionary_id(header).map(|id| {
+            dictionaries.get(&id).unwrap_or_else(|| {
+                panic!(
+                    "{} needs the zstd dictionary {}{}",
+                    filepath, id, DICT_SUFFIX
+                )
+            })
+        }),
+        _ => None,
+    };
+    match codec {
+        Some(codec) => Box::new(BufReader::new(compression::decoder(
+            reader,
+            codec,
+            dictionary.map(|d| d.as_slice()),
+        ))),
+        None => Box::new(reader),
+    }
+}
+
+pub fn read_file(filepath: &str) -> String {
+    let mut s = String::new();
+    open_file(filepath)
+        .read_to_string(&mut s)
+        .expect("Unable to read file as utf8");
+    s
+}
+
+/// Empty folder of the system temp dir for the files of one test.
+#[cfg(test)]
+pub fn test_dir(name: &str) -> String {
+    let dir = std::env::temp_dir().join(format!("rrr-test-{}-{}", std::process::id(), name));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir.to_string_lossy().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writers_of_a_store_under_any_suffix_share_a_lock() {
+        let dir = test_dir("locks");
+        let plain = format!("{}/out.json", dir);
+        let lock = lock_path(&plain);
+        assert_eq!(lock_path(&format!("{}/out.json.zst", dir)), lock);
+        assert_eq!(lock_path(&format!("{}/./out.json.gz", dir)), lock);
+        assert_ne!(lock_path(&format!("{}/other.json", dir)), lock);
+
+        let held = lock_store(&plain, Duration::ZERO).unwrap();
+        let zst = format!("{}/out.json.zst", dir);
+        assert!(lock_store(&zst, Duration::ZERO)
+            .unwrap_err()
+            .starts_with("Gave up"));
+        drop(held);
+        assert!(lock_store(&zst, Duration::ZERO).is_ok());
+    }
+
+    #[test]
+    fn archives_read_back_what_was_written() {
+        let dir = test_dir("archives");
+        let entries = vec![
+            (
+                "mA_i1_0.1.json".to_string(),
+                r#"{"model": "mA"}"#.to_string(),
+            ),
+            (
+                "mB_i2_0.5.json".to_string(),
+                r#"{"model": "mB"}"#.to_string(),
+            ),
+        ];
+        for compression in [None, Some(Compression::default())] {
+            let path = format!("{}/dump.tar", dir);
+            write_archive(&path, &entries, compression);
+            let written = match compression {
+                Some(c) => format!("{}{}", path, c.codec.suffix()),
+                None => path,
+            };
+            assert_eq!(read_archive(&written, |_| true), entries);
+            let some = read_archive(&written, |name| name.starts_with("mB"));
+            assert_eq!(some, entries[1..]);
+        }
+    }
+
+    #[test]
+    fn side_files_are_moved_into_the_archive_folder() {
+        let dir = test_dir("side-files");
+        let archive = format!("{}/done", dir);
+        let side = format!("{}/run.json", dir);
+        for content in ["first", "second"] {
+            fs::write(&side, content).unwrap();
+            remove_or_archive(&[&side], Some(&archive));
+            assert!(!Path::new(&side).exists());
+        }
+        // a name already archived gets a numbered suffix
+        assert_eq!(read_file(&format!("{}/run.json", archive)), "first");
+        assert_eq!(read_file(&format!("{}/run.json.1", archive)), "second");
+
+        fs::write(&side, "third").unwrap();
+        remove_or_archive(&[&side], None);
+        assert!(!Path::new(&side).exists());
+        assert_eq!(fs::read_dir(&archive).unwrap().count(), 2);
     }
 }